@@ -0,0 +1,129 @@
+/* Read/write latency of SeqLock against the usual alternatives for sharing a Copy value:
+ * RwLock, pointer swapping with ArcSwap (allocates on every write) and crossbeam's AtomicCell
+ * (falls back to a striped spinlock once the payload doesn't fit an atomic instruction).
+ * Payloads go from 8 bytes to 4 KiB.
+ * `read_contended` runs the reads while another thread keeps writing.
+ */
+use arc_swap::ArcSwap;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use crossbeam_utils::atomic::AtomicCell;
+use seqlock::SeqLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+
+trait Shared<T>: Send + Sync {
+    fn new(val: T) -> Self;
+    fn read(&self) -> T;
+    fn write(&self, val: T);
+}
+
+impl<T: Copy> Shared<T> for SeqLock<T> {
+    fn new(val: T) -> Self {
+        SeqLock::new(val)
+    }
+    fn read(&self) -> T {
+        self.get_reader().read()
+    }
+    fn write(&self, val: T) {
+        self.get_writer().write(val)
+    }
+}
+
+impl<T: Copy + Send + Sync> Shared<T> for RwLock<T> {
+    fn new(val: T) -> Self {
+        RwLock::new(val)
+    }
+    fn read(&self) -> T {
+        *RwLock::read(self).unwrap()
+    }
+    fn write(&self, val: T) {
+        *RwLock::write(self).unwrap() = val;
+    }
+}
+
+impl<T: Copy + Send + Sync> Shared<T> for ArcSwap<T> {
+    fn new(val: T) -> Self {
+        ArcSwap::from_pointee(val)
+    }
+    fn read(&self) -> T {
+        **self.load()
+    }
+    fn write(&self, val: T) {
+        self.store(Arc::new(val))
+    }
+}
+
+impl<T: Copy + Send> Shared<T> for AtomicCell<T> {
+    fn new(val: T) -> Self {
+        AtomicCell::new(val)
+    }
+    fn read(&self) -> T {
+        self.load()
+    }
+    fn write(&self, val: T) {
+        self.store(val)
+    }
+}
+
+fn bench_impl<S: Shared<[u64; W]> + 'static, const W: usize>(c: &mut Criterion, name: &str) {
+    let bytes = W * std::mem::size_of::<u64>();
+
+    let mut group = c.benchmark_group("read");
+    group.throughput(Throughput::Bytes(bytes as u64));
+    let shared = S::new([1; W]);
+    group.bench_function(BenchmarkId::new(name, bytes), |b| {
+        b.iter(|| black_box(shared.read()))
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("write");
+    group.throughput(Throughput::Bytes(bytes as u64));
+    let mut iteration = 0;
+    group.bench_function(BenchmarkId::new(name, bytes), |b| {
+        b.iter(|| {
+            iteration += 1;
+            shared.write(black_box([iteration; W]))
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("read_contended");
+    group.throughput(Throughput::Bytes(bytes as u64));
+    let shared = Arc::new(S::new([1; W]));
+    let stop = Arc::new(AtomicBool::new(false));
+    let writer_thread = {
+        let shared = shared.clone();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut iteration = 0;
+            while !stop.load(Ordering::Relaxed) {
+                iteration += 1;
+                shared.write([iteration; W]);
+            }
+        })
+    };
+    group.bench_function(BenchmarkId::new(name, bytes), |b| {
+        b.iter(|| black_box(shared.read()))
+    });
+    stop.store(true, Ordering::Relaxed);
+    writer_thread.join().unwrap();
+    group.finish();
+}
+
+fn bench_payload<const W: usize>(c: &mut Criterion) {
+    bench_impl::<SeqLock<[u64; W]>, W>(c, "seqlock");
+    bench_impl::<RwLock<[u64; W]>, W>(c, "rwlock");
+    bench_impl::<ArcSwap<[u64; W]>, W>(c, "arc_swap");
+    bench_impl::<AtomicCell<[u64; W]>, W>(c, "atomic_cell");
+}
+
+fn latency(c: &mut Criterion) {
+    bench_payload::<1>(c); // 8 B
+    bench_payload::<8>(c); // 64 B, one cache line
+    bench_payload::<64>(c); // 512 B
+    bench_payload::<512>(c); // 4 KiB
+}
+
+criterion_group!(benches, latency);
+criterion_main!(benches);
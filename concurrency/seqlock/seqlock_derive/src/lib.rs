@@ -0,0 +1,145 @@
+/* #[derive(SeqLockFields)] on `struct Quote { bid: f64, ask: f64 }` generates
+ *
+ *   QuoteSeqLock - owns a SeqLock<Quote>, one getter and one setter per field, each its own short
+ *                  optimistic read / write window, plus read_group() to read several fields in one retry loop
+ *   QuoteFields  - raw pointer view handed to read_group() closures, same getters. It lives in a hidden
+ *                  module so only read_group() can build one, and borrows the lock for as long as it lives
+ *
+ * Getters copy the single field with read_volatile instead of the whole struct.
+ * Field types must implement seqlock::Pod.
+ */
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Visibility};
+
+#[proc_macro_derive(SeqLockFields)]
+pub fn derive_seqlock_fields(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "SeqLockFields requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "SeqLockFields can only be derived for structs",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "SeqLockFields does not support generic structs",
+        ));
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let lock_name = format_ident!("{}SeqLock", name);
+    let view_name = format_ident!("{}Fields", name);
+
+    let names: Vec<_> = fields.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let setters: Vec<_> = names.iter().map(|n| format_ident!("set_{}", n)).collect();
+    let view_mod = format_ident!("__{}_seqlock_fields", name);
+    // the view is one module further down, private means visible to the parent there
+    let view_vis = match vis {
+        Visibility::Inherited => quote!(pub(super)),
+        Visibility::Public(_) => quote!(pub),
+        Visibility::Restricted(_) => quote!(pub(crate)),
+    };
+
+    Ok(quote! {
+        const _: fn() = || {
+            fn assert_pod<T: ::seqlock::Pod>() {}
+            #( assert_pod::<#types>(); )*
+        };
+
+        #vis struct #lock_name {
+            lock: ::seqlock::SeqLock<#name>,
+        }
+
+        #[doc(hidden)]
+        #[allow(non_snake_case, unused_imports)]
+        mod #view_mod {
+            use super::*;
+
+            #view_vis struct #view_name<'a> {
+                ptr: *const #name,
+                _lock: ::core::marker::PhantomData<&'a #name>,
+            }
+
+            impl<'a> #view_name<'a> {
+                // ptr must stay readable for 'a, read_group() only hands out views inside a read window
+                pub(super) unsafe fn new(ptr: *const #name) -> #view_name<'a> {
+                    #view_name {
+                        ptr,
+                        _lock: ::core::marker::PhantomData,
+                    }
+                }
+
+                #(
+                    #view_vis fn #names(&self) -> #types {
+                        unsafe { ::core::ptr::read_volatile(::core::ptr::addr_of!((*self.ptr).#names)) }
+                    }
+                )*
+            }
+        }
+
+        #vis use #view_mod::#view_name;
+
+        impl #lock_name {
+            #vis fn new(val: #name) -> #lock_name {
+                #lock_name {
+                    lock: ::seqlock::SeqLock::new(val),
+                }
+            }
+
+            #vis fn lock(&self) -> &::seqlock::SeqLock<#name> {
+                &self.lock
+            }
+
+            #vis fn read(&self) -> #name {
+                self.lock.get_reader().read()
+            }
+
+            #vis fn write(&self, val: #name) {
+                self.lock.get_writer().write(val)
+            }
+
+            // several fields consistent with each other, `read` may run more than once
+            #vis fn read_group<R>(&self, read: impl for<'v> Fn(&#view_name<'v>) -> R) -> R {
+                unsafe {
+                    self.lock
+                        .get_reader()
+                        .read_projected(|ptr| read(&#view_name::new(ptr)))
+                }
+            }
+
+            #(
+                #vis fn #names(&self) -> #types {
+                    self.read_group(|fields| fields.#names())
+                }
+
+                #vis fn #setters(&self, val: #types) {
+                    self.lock.get_writer().write_with(|item| unsafe {
+                        ::core::ptr::addr_of_mut!((*item).#names).write(val)
+                    })
+                }
+            )*
+        }
+    })
+}
@@ -0,0 +1,194 @@
+/* Delta publishing for large values (e.g. 4 KiB order book snapshots).
+ * The value is split into 64 byte lines and every line remembers the counter value of the write that last
+ * changed it. A reader keeping a LocalCopy only re-copies lines stamped after its own copy.
+ * Lines are offsets into T, not hardware cache lines: the value itself isn't necessarily 64 byte aligned.
+ */
+use crate::{SeqLockReader, SeqLockWriter};
+
+use crate::sync::atomic::{fence, Ordering};
+
+use std::mem::size_of;
+
+const LINE: usize = 64;
+
+pub(crate) fn line_count<T>() -> usize {
+    size_of::<T>().div_ceil(LINE)
+}
+
+fn line_range<T>(line: usize) -> (usize, usize) {
+    let start = line * LINE;
+    (start, LINE.min(size_of::<T>() - start))
+}
+
+impl<T: Copy> SeqLockWriter<'_, T> {
+    // counter value readers will see once this write ends
+    fn _publish_seq(&self) -> usize {
+        self.iteration.load(Ordering::Relaxed) + 1
+    }
+
+    pub(crate) fn _mark_all_dirty(&self) {
+        let seq = self._publish_seq();
+        self.lines.iter().for_each(|line| line.store(seq, Ordering::Relaxed));
+    }
+
+    // padding bytes take part in the comparison, at worst a line is copied needlessly
+    pub(crate) fn _mark_dirty_lines(&self, old: &T, new: &T) {
+        let seq = self._publish_seq();
+        let old = old as *const T as *const u8;
+        let new = new as *const T as *const u8;
+        for (i, line) in self.lines.iter().enumerate() {
+            let (start, len) = line_range::<T>(i);
+            let changed = unsafe {
+                std::slice::from_raw_parts(old.add(start), len)
+                    != std::slice::from_raw_parts(new.add(start), len)
+            };
+            if changed {
+                line.store(seq, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// reader side copy of a seqlocked value, kept up to date by refresh()
+pub struct LocalCopy<T> {
+    val: T,
+    seq: usize, // counter value `val` is consistent with
+}
+
+impl<T: Copy> LocalCopy<T> {
+    pub fn new(reader: &SeqLockReader<'_, T>) -> LocalCopy<T> {
+        let mut val = std::mem::MaybeUninit::uninit();
+        let mut step = 0;
+        loop {
+            if let Some(seq) = reader._try_read_at(val.as_mut_ptr()) {
+                return LocalCopy {
+                    val: unsafe { val.assume_init() },
+                    seq,
+                };
+            }
+            reader._wait(&mut step, None);
+        }
+    }
+
+    pub fn get(&self) -> &T {
+        &self.val
+    }
+
+    /* Brings the copy up to date, returns the number of lines copied.
+     * Without dirty tracking (SeqLock::new) any change copies the whole value.
+     */
+    pub fn refresh(&mut self, reader: &SeqLockReader<'_, T>) -> usize {
+        let mut step = 0;
+        loop {
+            if let Some(copied) = self._try_refresh(reader) {
+                return copied;
+            }
+            reader._wait(&mut step, None);
+        }
+    }
+
+    /* Lines stamped after self.seq are copied straight into self.val. A torn attempt leaves garbage only in
+     * such lines, and stamps never go back, so the next attempt copies all of them again.
+     */
+    fn _try_refresh(&mut self, reader: &SeqLockReader<'_, T>) -> Option<usize> {
+        let prev = reader.iteration.load(Ordering::Acquire);
+        if prev & 1 == 1 {
+            return None;
+        }
+        if prev == self.seq {
+            return Some(0);
+        }
+        let dst = &mut self.val as *mut T;
+        let copied = if reader.lines.is_empty() {
+            unsafe { reader.item.read_into(dst) };
+            line_count::<T>()
+        } else {
+            let mut copied = 0;
+            for (i, line) in reader.lines.iter().enumerate() {
+                if line.load(Ordering::Relaxed) > self.seq {
+                    let (start, len) = line_range::<T>(i);
+                    unsafe { reader.item.read_bytes_into(dst, start, len) };
+                    copied += 1;
+                }
+            }
+            copied
+        };
+        fence(Ordering::Acquire);
+        if prev != reader.iteration.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.seq = prev;
+        Some(copied)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::stress::stress_iterations;
+    use crate::{LocalCopy, SeqLock};
+    use std::sync::Arc;
+    use std::thread;
+
+    const WORDS: usize = 512; // 4 KiB, 64 lines
+    const WORDS_PER_LINE: usize = 8;
+
+    #[test]
+    fn test_update_in_place() {
+        let my_lock = SeqLock::new([0u64; 4]);
+        my_lock.get_writer().update(|item| item[2] = 7);
+        let reader = my_lock.get_reader();
+        assert_eq!(reader.read_versioned(), ([0, 0, 7, 0], 1));
+    }
+
+    #[test]
+    fn test_refresh_copies_dirty_lines_only() {
+        let my_lock = SeqLock::with_dirty_tracking([0u64; WORDS]);
+        let reader = my_lock.get_reader();
+        let mut local = LocalCopy::new(&reader);
+        assert_eq!(local.refresh(&reader), 0);
+
+        my_lock.get_writer().update(|item| {
+            item[0] = 1;
+            item[3 * WORDS_PER_LINE + 1] = 2;
+        });
+        my_lock.get_writer().update(|item| item[WORDS - 1] = 3);
+        assert_eq!(local.refresh(&reader), 3);
+        assert_eq!(local.get(), &reader.read());
+
+        my_lock.get_writer().write([9; WORDS]);
+        assert_eq!(local.refresh(&reader), WORDS / WORDS_PER_LINE);
+        assert_eq!(local.get(), &[9; WORDS]);
+    }
+
+    #[test]
+    fn test_refresh_with_concurrent_updates() {
+        let my_lock = Arc::new(SeqLock::with_dirty_tracking([0u64; WORDS]));
+        let iterations = stress_iterations(200000);
+
+        // every update sets two words in different lines to the same number
+        let lock_writer = my_lock.clone();
+        let writer_thread = thread::spawn(move || {
+            for i in 1..=iterations {
+                let line = i as usize % (WORDS / WORDS_PER_LINE - 1);
+                lock_writer.get_writer().update(|item| {
+                    item[0] = i;
+                    item[(line + 1) * WORDS_PER_LINE] = i;
+                });
+            }
+        });
+
+        let reader = my_lock.get_reader();
+        let mut local = LocalCopy::new(&reader);
+        loop {
+            local.refresh(&reader);
+            let val = local.get();
+            let latest = val.iter().step_by(WORDS_PER_LINE).skip(1).max().unwrap();
+            assert_eq!(val[0], *latest);
+            if val[0] == iterations {
+                break;
+            }
+        }
+        writer_thread.join().unwrap();
+        assert_eq!(local.get(), &my_lock.get_reader().read());
+    }
+}
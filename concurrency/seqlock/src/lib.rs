@@ -0,0 +1,914 @@
+mod delta;
+mod parking;
+mod ring;
+#[cfg(all(test, not(loom)))]
+mod stress;
+#[cfg(feature = "async")]
+mod subscribe;
+mod sync;
+
+pub use delta::LocalCopy;
+pub use ring::{RingError, SeqLockRing, SeqLockRingConsumer, SeqLockRingProducer};
+#[cfg(feature = "derive")]
+pub use seqlock_derive::SeqLockFields;
+#[cfg(feature = "async")]
+pub use subscribe::{Changed, Snapshots};
+
+use parking::{Backoff, Parking};
+
+use sync::atomic::{AtomicU64, AtomicUsize, Ordering, fence};
+use sync::ItemCell;
+
+use std::time::{Duration, Instant};
+
+use std::mem::MaybeUninit;
+
+// use std::ptr::NonNull;
+//use std::ops::{Deref, DerefMut};
+
+type Counter = AtomicUsize;
+
+#[repr(align(64))]
+pub struct SeqLock<T> {
+    pub(crate) iteration: Counter,
+    item: ItemCell<T>, // modified
+    version: AtomicU64, // modified together with item
+    lines: Box<[AtomicUsize]>, // dirty tracking only, see delta.rs
+    parking: Parking,
+}
+
+// required to make ItemCell safe for multithreaded access
+unsafe impl<T> Send for SeqLock<T> {}
+unsafe impl<T> Sync for SeqLock<T> {}
+
+impl<T: Copy> SeqLock<T> {
+    pub fn new(val: T) -> SeqLock<T> {
+        SeqLock {
+            item: ItemCell::new(val),
+            iteration: AtomicUsize::new(0),
+            version: AtomicU64::new(0),
+            lines: Box::new([]),
+            parking: Parking::new(),
+        }
+    }
+
+    // records which 64 byte lines each write changed, so LocalCopy::refresh copies only those
+    pub fn with_dirty_tracking(val: T) -> SeqLock<T> {
+        SeqLock {
+            lines: (0..delta::line_count::<T>()).map(|_| AtomicUsize::new(0)).collect(),
+            ..SeqLock::new(val)
+        }
+    }
+
+    // blocks (spinning with backoff) while another writer holds the lock
+    pub fn get_writer(&self) -> SeqLockWriter<'_, T> {
+        let obj = SeqLockWriter {
+            item: &self.item,
+            iteration: &self.iteration,
+            version: &self.version,
+            lines: &self.lines,
+            parking: &self.parking,
+        };
+        obj._start_write();
+        obj
+    }
+
+    // fails instead of spinning when another writer holds the lock
+    pub fn try_get_writer(&self) -> Option<SeqLockWriter<'_, T>> {
+        let obj = SeqLockWriter {
+            item: &self.item,
+            iteration: &self.iteration,
+            version: &self.version,
+            lines: &self.lines,
+            parking: &self.parking,
+        };
+        if obj._try_start_write() {
+            Some(obj)
+        } else {
+            std::mem::forget(obj); // lock not taken, so _end_write must not run
+            None
+        }
+    }
+    pub fn get_reader(&self) -> SeqLockReader<'_, T> {
+        SeqLockReader {
+            item: &self.item,
+            iteration: &self.iteration,
+            version: &self.version,
+            lines: &self.lines,
+            parking: &self.parking,
+            policy: WaitPolicy::default(),
+        }
+    }
+}
+
+pub struct SeqLockWriter<'a, T: Copy> {
+    iteration: &'a Counter,
+    item: &'a ItemCell<T>,
+    version: &'a AtomicU64,
+    lines: &'a [AtomicUsize],
+    parking: &'a Parking,
+}
+
+impl<T: Copy> SeqLockWriter<'_, T> {
+    // consuming. lock is held until the writer is dropped
+    pub fn write(self, val: T) {
+        unsafe { self.item.write(val) };
+        self._bump_version();
+        self._mark_all_dirty();
+    }
+
+    // consuming. publishes under the caller's version (e.g. exchange sequence number) instead of bumping it
+    pub fn write_versioned(self, val: T, version: u64) {
+        unsafe { self.item.write(val) };
+        self.version.store(version, Ordering::Relaxed);
+        self._mark_all_dirty();
+    }
+
+    // consuming.
+    pub fn write_with(self, closure: impl Fn(*mut T)) {
+        unsafe { self.item.with_mut(closure) };
+        self._bump_version();
+        self._mark_all_dirty();
+    }
+
+    /* consuming. mutates the value in place, readers retry until the writer is dropped.
+     * With dirty tracking the old value is kept aside and compared line by line afterwards.
+     */
+    pub fn update(self, closure: impl FnOnce(&mut T)) {
+        unsafe {
+            self.item.with_mut(|item| {
+                let item = &mut *item;
+                if self.lines.is_empty() {
+                    closure(item);
+                } else {
+                    let old = *item;
+                    closure(item);
+                    self._mark_dirty_lines(&old, item);
+                }
+            })
+        };
+        self._bump_version();
+    }
+
+    // relaxed is enough, the version is read inside the read window like the item
+    fn _bump_version(&self) {
+        let version = self.version.load(Ordering::Relaxed);
+        self.version.store(version.wrapping_add(1), Ordering::Relaxed);
+    }
+
+    /* Writers serialize on the counter itself: an odd value means the lock is taken,
+     * so acquiring is a CAS from even to odd. Spin with backoff while another writer holds it.
+     * The weak CAS may fail spuriously, which only costs another round of the loop.
+     */
+    fn _start_write(&self) {
+        let mut backoff = Backoff::new();
+        loop {
+            let prev = self.iteration.load(Ordering::Relaxed);
+            if prev & 1 == 1 {
+                backoff.snooze();
+                continue;
+            }
+            if self
+                .iteration
+                .compare_exchange_weak(prev, prev + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                break;
+            }
+            backoff.snooze();
+        }
+        fence(Ordering::Release);
+    }
+
+    /* One shot, so the CAS is strong: a spurious failure (LL/SC targets like aarch64) would report
+     * a free lock as taken.
+     */
+    fn _try_start_write(&self) -> bool {
+        let prev = self.iteration.load(Ordering::Relaxed);
+        if prev & 1 == 1 {
+            return false;
+        }
+        // acquire pairs with the release in _end_write of the previous writer,
+        // so its item update happens-before ours
+        if self
+            .iteration
+            .compare_exchange(prev, prev + 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        /* A release operation only needs to prevent preceding memory operations from being reordered past itself,
+         * but a release fence must prevent preceding memory operations from being reordered past all subsequent writes
+         */
+        fence(Ordering::Release);
+        true
+    }
+
+    fn _end_write(&self) {
+        debug_assert!(
+            self.iteration.load(Ordering::Relaxed) & 1 == 1,
+            "writer must hold the lock"
+        );
+
+        // SeqCst orders the increment before the waiters check in unpark_all (see Parking::park)
+        self.iteration.fetch_add(1, Ordering::SeqCst);
+        self.parking.unpark_all();
+    }
+}
+
+impl<T: Copy> Drop for SeqLockWriter<'_, T> {
+    fn drop(&mut self) {
+        self._end_write();
+    }
+}
+
+/* How a reader waits for a writer to finish: `spins` busy iterations, then `yields` calls to
+ * thread::yield_now(), then parks on a futex until the writer's _end_write wakes it up.
+ */
+#[derive(Clone, Copy, Debug)]
+pub struct WaitPolicy {
+    pub spins: u32,
+    pub yields: u32,
+}
+
+impl Default for WaitPolicy {
+    fn default() -> WaitPolicy {
+        WaitPolicy {
+            spins: 64,
+            yields: 16,
+        }
+    }
+}
+
+/// Plain old data: Copy and every bit pattern is a valid value, so a field copied in the middle of a write
+/// is garbage but never undefined behaviour. Required for fields of `#[derive(SeqLockFields)]` structs.
+///
+/// # Safety
+/// Implement only for types without invalid bit patterns, padding or pointers.
+pub unsafe trait Pod: Copy + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),*) => { $(unsafe impl Pod for $t {})* };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+pub struct SeqLockReader<'a, T> {
+    iteration: &'a Counter,
+    item: &'a ItemCell<T>,
+    version: &'a AtomicU64,
+    lines: &'a [AtomicUsize],
+    parking: &'a Parking,
+    policy: WaitPolicy,
+}
+
+impl<T: Copy> SeqLockReader<'_, T> {
+    pub fn with_wait_policy(mut self, policy: WaitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn read(&self) -> T {
+        unsafe {
+            let mut val: MaybeUninit<T> = MaybeUninit::uninit();
+            let mut step = 0;
+            while !self._try_read(val.as_mut_ptr()) {
+                self._wait(&mut step, None);
+            }
+            *val.as_mut_ptr()
+        }
+    }
+
+    pub fn read_into(&self, val : &mut T) {
+        let mut step = 0;
+        while !self._try_read(val as *mut _) {
+            self._wait(&mut step, None);
+        }
+    }
+
+    // None if a writer kept the lock for the whole timeout
+    pub fn read_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let mut val: MaybeUninit<T> = MaybeUninit::uninit();
+        let mut step = 0;
+        while !self._try_read(val.as_mut_ptr()) {
+            if !self._wait(&mut step, Some(deadline)) {
+                return None;
+            }
+        }
+        Some(unsafe { val.assume_init() })
+    }
+
+    /// Copies only what `project` reads out of the value, retrying like read().
+    ///
+    /// # Safety
+    /// A writer may be modifying the value while `project` runs, so it must only read through the pointer
+    /// (ptr::addr_of! + ptr::read_volatile) and never create references to it. Whatever it reads must be
+    /// valid for any bit pattern, see Pod. Results of torn attempts are dropped.
+    pub unsafe fn read_projected<R>(&self, project: impl Fn(*const T) -> R) -> R {
+        let mut step = 0;
+        loop {
+            if let Some((val, _)) = self._try_read_with(|| self.item.project(&project)) {
+                return val;
+            }
+            self._wait(&mut step, None);
+        }
+    }
+
+    pub fn read_versioned(&self) -> (T, u64) {
+        let mut val: MaybeUninit<T> = MaybeUninit::uninit();
+        let mut step = 0;
+        loop {
+            if let Some((version, _)) = self._try_read_versioned(val.as_mut_ptr()) {
+                return (unsafe { val.assume_init() }, version);
+            }
+            self._wait(&mut step, None);
+        }
+    }
+
+    // version of the published value without copying it. 0 is the initial value
+    pub fn version(&self) -> u64 {
+        let mut step = 0;
+        loop {
+            if let Some((version, _)) = self._try_read_with(|| self.version.load(Ordering::Relaxed)) {
+                return version;
+            }
+            self._wait(&mut step, None);
+        }
+    }
+
+    pub fn has_changed_since(&self, version: u64) -> bool {
+        self.version() != version
+    }
+
+    /* Blocks until a version different from `last_version` is published,
+     * returns the value and its version to pass into the next call.
+     */
+    pub fn read_changed(&self, last_version: u64) -> (T, u64) {
+        let mut val: MaybeUninit<T> = MaybeUninit::uninit();
+        let mut step = 0;
+        loop {
+            match self._try_read_versioned(val.as_mut_ptr()) {
+                Some((version, _)) if version != last_version => {
+                    return (unsafe { val.assume_init() }, version)
+                }
+                Some((_, seq)) => self._wait_while(seq, &mut step, None),
+                None => self._wait(&mut step, None),
+            };
+        }
+    }
+
+    // waits for the writer currently holding the lock, false once the deadline has passed
+    pub(crate) fn _wait(&self, step: &mut u32, deadline: Option<Instant>) -> bool {
+        let observed = self.iteration.load(Ordering::Relaxed);
+        if observed & 1 == 0 {
+            return true; // torn read, writer already finished, retry right away
+        }
+        self._wait_while(observed, step, deadline)
+    }
+
+    // one round of spin/yield/park while the counter equals `observed`
+    fn _wait_while(&self, observed: usize, step: &mut u32, deadline: Option<Instant>) -> bool {
+        let timeout = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(left) if !left.is_zero() => Some(left),
+                _ => return false,
+            },
+            None => None,
+        };
+        if *step < self.policy.spins {
+            sync::spin_loop();
+        } else if *step < self.policy.spins + self.policy.yields {
+            sync::yield_now();
+        } else {
+            self.parking.park(self.iteration, observed, timeout);
+        }
+        *step = step.saturating_add(1);
+        true
+    }
+
+    pub fn try_read(&self) -> Option<T> {
+        let mut val : Option<T> = None;
+        self.try_read_into(&mut val);
+        val
+    }
+
+    pub fn try_read_into(&self, val: &mut Option<T>) {
+        unsafe {
+            //let my_ref = val.get_or_insert(Default::default());
+            let mut data: MaybeUninit<T> = MaybeUninit::uninit();
+            let success = self._try_read(data.as_mut_ptr());
+            if !success {
+                *val = None;
+            } else {
+                *val = Some(*data.as_mut_ptr());
+            }
+
+        }
+    }
+
+    fn _try_read(&self, val: *mut T) -> bool {
+        self._try_read_at(val).is_some()
+    }
+
+    // on success returns the (even) counter value the copy is consistent with
+    pub(crate) fn _try_read_at(&self, val: *mut T) -> Option<usize> {
+        self._try_read_with(|| unsafe { self.item.read_into(val) })
+            .map(|(_, seq)| seq)
+    }
+
+    fn _try_read_versioned(&self, val: *mut T) -> Option<(u64, usize)> {
+        self._try_read_with(|| unsafe {
+            self.item.read_into(val);
+            self.version.load(Ordering::Relaxed)
+        })
+    }
+
+    // runs `copy` inside the optimistic read window, its result is only valid when Some is returned
+    #[inline(always)]
+    fn _try_read_with<R>(&self, copy: impl FnOnce() -> R) -> Option<(R, usize)> {
+        let prev = self.iteration.load(Ordering::Acquire);
+        if prev & 1 == 0 {
+            let copied = copy();
+            fence(Ordering::Acquire);
+            if prev == self.iteration.load(Ordering::Relaxed) {
+                return Some((copied, prev));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::*;
+    use crate::stress::stress_iterations;
+    use std::convert::TryInto;
+    use std::sync::{mpsc, Arc, Barrier};
+    use std::thread;
+
+    struct TestWriter {
+        data: Vec<u64>,
+    }
+
+    impl TestWriter {
+        fn new(num: usize) -> TestWriter {
+            let mut obj = TestWriter { data: vec![0; num] };
+            obj.generate_consecutive_numbers(0);
+            obj
+        }
+
+        fn generate_consecutive_numbers(&mut self, start: u64) {
+            let mut idx = 0;
+            self.data.iter_mut().for_each(|e| {
+                *e = idx + start;
+                idx += 1;
+            });
+        }
+
+        fn are_numbers_in_increasing_order(data: &[u64]) {
+            data.iter().enumerate().skip(1).fold(data[0], |prev, (i, next)| {
+                if prev + 1 != *next {
+                    panic!("idx={} not equal {:?} != {:?}", i, prev, *next);
+                }
+                *next
+            });
+        }
+    }
+
+    #[test]
+    fn test_single_consumer_one_cacheline() {
+        const ARRAY_SIZE: usize = 8;
+
+        let mut data_writer = TestWriter::new(ARRAY_SIZE);
+        let my_lock = Arc::new(SeqLock::<[u64; ARRAY_SIZE]>::new(
+            data_writer.data.clone().try_into().unwrap(),
+        ));
+
+        let iterations = stress_iterations(1000000);
+
+        let lock_writer = my_lock.clone();
+        let writer_thread = thread::spawn(move || {
+            for i in 0..iterations {
+                data_writer.generate_consecutive_numbers(i);
+                lock_writer.get_writer().write_with(|item| unsafe {
+                    *item = data_writer.data.as_slice().try_into().unwrap();
+                });
+            }
+        });
+
+        {
+            let reader = my_lock.get_reader();
+            for _ in 0..iterations {
+                let value = reader.read();
+                TestWriter::are_numbers_in_increasing_order(&value);
+            }
+        }
+
+        writer_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_single_consumer_one_cacheline_try() {
+        const ARRAY_SIZE: usize = 8;
+
+        let mut data_writer = TestWriter::new(ARRAY_SIZE);
+        let my_lock = Arc::new(SeqLock::<[u64; ARRAY_SIZE]>::new(
+            data_writer.data.clone().try_into().unwrap(),
+        ));
+
+        let iterations = stress_iterations(1000000);
+
+        let lock_writer = my_lock.clone();
+        let writer_thread = thread::spawn(move || {
+            for i in 0..iterations {
+                data_writer.generate_consecutive_numbers(i);
+                lock_writer.get_writer().write_with(|item| unsafe {
+                    *item = data_writer.data.as_slice().try_into().unwrap();
+                });
+            }
+        });
+
+        {
+            let mut my_optional = Default::default();
+            let reader = my_lock.get_reader();
+            for _ in 0..iterations {
+                reader.try_read_into(&mut my_optional);
+                if let Some(ref value) = my_optional {
+                    TestWriter::are_numbers_in_increasing_order(value);
+                }
+
+            }
+        }
+
+        writer_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_multiple_writers_one_cacheline() {
+        const ARRAY_SIZE: usize = 8;
+        const WRITERS: u64 = 4;
+
+        let data_writer = TestWriter::new(ARRAY_SIZE);
+        let my_lock = Arc::new(SeqLock::<[u64; ARRAY_SIZE]>::new(
+            data_writer.data.clone().try_into().unwrap(),
+        ));
+
+        let iterations = stress_iterations(1000000);
+
+        let writer_threads: Vec<_> = (0..WRITERS)
+            .map(|w| {
+                let lock_writer = my_lock.clone();
+                let mut data_writer = TestWriter::new(ARRAY_SIZE);
+                thread::spawn(move || {
+                    for i in 0..iterations {
+                        data_writer.generate_consecutive_numbers(i * WRITERS + w);
+                        lock_writer.get_writer().write_with(|item| unsafe {
+                            *item = data_writer.data.as_slice().try_into().unwrap();
+                        });
+                    }
+                })
+            })
+            .collect();
+
+        {
+            let reader = my_lock.get_reader();
+            for _ in 0..iterations {
+                let value = reader.read();
+                TestWriter::are_numbers_in_increasing_order(&value);
+            }
+        }
+
+        for writer_thread in writer_threads {
+            writer_thread.join().unwrap();
+        }
+
+        let writes = WRITERS * iterations;
+        assert_eq!(my_lock.iteration.load(Ordering::Relaxed) as u64, 2 * writes);
+    }
+
+    #[test]
+    fn test_try_get_writer() {
+        let my_lock = SeqLock::new(1u64);
+
+        let writer = my_lock.try_get_writer().expect("lock is free");
+        assert!(my_lock.try_get_writer().is_none());
+        assert!(my_lock.get_reader().try_read().is_none());
+        writer.write(2);
+
+        assert_eq!(my_lock.get_reader().read(), 2);
+        my_lock.try_get_writer().expect("lock released on drop").write(3);
+        assert_eq!(my_lock.get_reader().read(), 3);
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let my_lock = SeqLock::new(1u64);
+        let reader = my_lock.get_reader();
+
+        let writer = my_lock.get_writer();
+        assert_eq!(reader.read_timeout(Duration::from_millis(20)), None);
+        writer.write(2);
+        assert_eq!(reader.read_timeout(Duration::from_millis(20)), Some(2));
+    }
+
+    #[test]
+    fn test_parked_reader_woken_by_writer() {
+        let my_lock = Arc::new(SeqLock::new(1u64));
+        let writer = my_lock.get_writer();
+
+        let lock_reader = my_lock.clone();
+        let reader_thread = thread::spawn(move || {
+            let reader = lock_reader
+                .get_reader()
+                .with_wait_policy(WaitPolicy { spins: 0, yields: 0 });
+            reader.read()
+        });
+
+        thread::sleep(Duration::from_millis(50));
+        writer.write(2);
+        assert_eq!(reader_thread.join().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_read_changed() {
+        let my_lock = Arc::new(SeqLock::new(0u64));
+        let (sender, receiver) = mpsc::channel();
+
+        let lock_reader = my_lock.clone();
+        let reader_thread = thread::spawn(move || {
+            let reader = lock_reader.get_reader();
+            let (first, version) = reader.read_changed(0);
+            sender.send(first).unwrap();
+            reader.read_changed(version)
+        });
+
+        thread::sleep(Duration::from_millis(20));
+        my_lock.get_writer().write(1);
+        assert_eq!(receiver.recv().unwrap(), 1);
+        thread::sleep(Duration::from_millis(20));
+        my_lock.get_writer().write(2);
+        assert_eq!(reader_thread.join().unwrap(), (2, 2));
+    }
+
+    #[test]
+    fn test_versioned_reads() {
+        let my_lock = SeqLock::new(0u64);
+        let reader = my_lock.get_reader();
+        assert_eq!(reader.read_versioned(), (0, 0));
+
+        my_lock.get_writer().write(10);
+        let (value, version) = reader.read_versioned();
+        assert_eq!((value, version), (10, 1));
+        assert!(!reader.has_changed_since(version));
+
+        my_lock.get_writer().write_versioned(20, 1000);
+        assert!(reader.has_changed_since(version));
+        assert_eq!(reader.version(), 1000);
+        assert_eq!(reader.read_changed(version), (20, 1000));
+
+        my_lock.get_writer().write(30);
+        assert_eq!(reader.read_versioned(), (30, 1001));
+    }
+
+    #[test]
+    fn test_ring_lagged_and_resync() {
+        let ring = SeqLockRing::<u64, 4>::new(0);
+        let mut producer = ring.get_producer();
+        let mut consumer = ring.get_consumer();
+
+        assert_eq!(consumer.try_read_next(), Err(RingError::Empty));
+        for i in 0..3 {
+            producer.push(i);
+        }
+        for i in 0..3 {
+            assert_eq!(consumer.try_read_next(), Ok(i));
+        }
+        assert_eq!(consumer.try_read_next(), Err(RingError::Empty));
+
+        for i in 3..9 {
+            producer.push(i);
+        }
+        assert_eq!(consumer.try_read_next(), Err(RingError::Lagged(3)));
+        assert_eq!(consumer.cursor(), 3);
+        assert_eq!(consumer.resync(), 3);
+        for i in 6..9 {
+            assert_eq!(consumer.try_read_next(), Ok(i));
+        }
+        assert_eq!(consumer.try_read_next(), Err(RingError::Empty));
+    }
+
+    #[test]
+    fn test_ring_reader_keeping_up_never_lags() {
+        // long copies, the producer often ends a write while the reader copies the same slot
+        const ARRAY_SIZE: usize = 512;
+        const SLOTS: usize = 8;
+
+        let mut data_writer = TestWriter::new(ARRAY_SIZE);
+        let ring = Arc::new(SeqLockRing::<[u64; ARRAY_SIZE], SLOTS>::new(
+            data_writer.data.clone().try_into().unwrap(),
+        ));
+        let consumed = Arc::new(AtomicUsize::new(0));
+
+        let iterations = stress_iterations(100000);
+
+        let subscribed = Arc::new(Barrier::new(2));
+        let consumer_thread = {
+            let ring = ring.clone();
+            let consumed = consumed.clone();
+            let subscribed = subscribed.clone();
+            thread::spawn(move || {
+                let mut consumer = ring.get_consumer();
+                subscribed.wait();
+                for i in 0..iterations {
+                    let value = consumer.read_next().unwrap();
+                    TestWriter::are_numbers_in_increasing_order(&value);
+                    assert_eq!(value[0], i);
+                    consumed.store(i as usize + 1, Ordering::Release);
+                }
+            })
+        };
+
+        subscribed.wait();
+        {
+            // one item at a time, the reader waits on the slot being written and is never lapped
+            let mut producer = ring.get_producer();
+            for i in 0..iterations {
+                while (i as usize) > consumed.load(Ordering::Acquire) {
+                    thread::yield_now();
+                }
+                data_writer.generate_consecutive_numbers(i);
+                producer.push(data_writer.data.as_slice().try_into().unwrap());
+            }
+        }
+        consumer_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_ring_of_large_items() {
+        let ring = Arc::new(SeqLockRing::<[u64; 512], 1024>::new([0; 512]));
+        let mut consumer = ring.get_consumer();
+        ring.get_producer().push([7; 512]);
+        assert_eq!(consumer.try_read_next(), Ok([7; 512]));
+    }
+
+    #[test]
+    fn test_ring_broadcast_multiple_consumers() {
+        const ARRAY_SIZE: usize = 8;
+        const CONSUMERS: usize = 3;
+
+        let mut data_writer = TestWriter::new(ARRAY_SIZE);
+        let ring = Arc::new(SeqLockRing::<[u64; ARRAY_SIZE], 64>::new(
+            data_writer.data.clone().try_into().unwrap(),
+        ));
+
+        let iterations = stress_iterations(1000000);
+
+        let subscribed = Arc::new(Barrier::new(CONSUMERS + 1));
+        let consumer_threads: Vec<_> = (0..CONSUMERS)
+            .map(|_| {
+                let ring = ring.clone();
+                let subscribed = subscribed.clone();
+                thread::spawn(move || {
+                    let mut consumer = ring.get_consumer();
+                    subscribed.wait();
+                    let mut last = None;
+                    while last != Some(iterations - 1) {
+                        match consumer.read_next() {
+                            Ok(value) => {
+                                TestWriter::are_numbers_in_increasing_order(&value);
+                                assert!(last.is_none_or(|last| value[0] > last));
+                                last = Some(value[0]);
+                            }
+                            Err(RingError::Lagged(_)) => {
+                                consumer.resync();
+                            }
+                            Err(RingError::Empty) => unreachable!(),
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        subscribed.wait();
+        {
+            let mut producer = ring.get_producer();
+            for i in 0..iterations {
+                data_writer.generate_consecutive_numbers(i);
+                producer.push(data_writer.data.as_slice().try_into().unwrap());
+            }
+        }
+
+        for consumer_thread in consumer_threads {
+            consumer_thread.join().unwrap();
+        }
+    }
+}
+
+/* Exhaustive interleavings of _start_write/_end_write/_try_read under loom, with the item modeled as relaxed
+ * atomic bytes (see sync.rs), so a reordered or half finished copy shows up as a torn value:
+ * RUSTFLAGS="--cfg loom" cargo test --release --lib loom
+ */
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use super::*;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    fn model(f: impl Fn() + Sync + Send + 'static) {
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(f);
+    }
+
+    #[test]
+    fn loom_reader_never_sees_torn_value() {
+        model(|| {
+            let my_lock = Arc::new(SeqLock::new([0u8; 2]));
+
+            let lock_writer = my_lock.clone();
+            let writer_thread = thread::spawn(move || {
+                lock_writer.get_writer().write([1, 1]);
+                lock_writer.get_writer().write([2, 2]);
+            });
+
+            let reader = my_lock.get_reader();
+            if let Some(value) = reader.try_read() {
+                assert_eq!(value[0], value[1]);
+            }
+            let mut value = MaybeUninit::<[u8; 2]>::uninit();
+            if let Some((version, _)) = reader._try_read_versioned(value.as_mut_ptr()) {
+                let value = unsafe { value.assume_init() };
+                assert_eq!((value[0] as u64, value[1] as u64), (version, version));
+            }
+            writer_thread.join().unwrap();
+            assert_eq!(reader.read_versioned(), ([2, 2], 2));
+        });
+    }
+
+    #[test]
+    fn loom_multiple_writers_serialize() {
+        model(|| {
+            let my_lock = Arc::new(SeqLock::new([0u8; 2]));
+
+            let writer_threads: Vec<_> = (1..=2u8)
+                .map(|w| {
+                    let lock_writer = my_lock.clone();
+                    thread::spawn(move || lock_writer.get_writer().update(|item| *item = [w, w]))
+                })
+                .collect();
+
+            if let Some(value) = my_lock.get_reader().try_read() {
+                assert_eq!(value[0], value[1]);
+            }
+            for writer_thread in writer_threads {
+                writer_thread.join().unwrap();
+            }
+            assert_eq!(my_lock.iteration.load(Ordering::Relaxed), 4);
+            assert_eq!(my_lock.get_reader().version(), 2);
+        });
+    }
+
+    #[test]
+    fn loom_ring_reader_keeping_up_never_lags() {
+        model(|| {
+            let ring = Arc::new(SeqLockRing::<[u8; 2], 2>::new([0, 0]));
+            let mut consumer = ring.get_consumer();
+
+            let ring_producer = ring.clone();
+            let producer_thread = thread::spawn(move || ring_producer.get_producer().push([1, 1]));
+
+            // a push ending during the copy is no lap
+            match consumer.try_read_next() {
+                Ok(value) => assert_eq!(value, [1, 1]),
+                result => assert_eq!(result, Err(RingError::Empty)),
+            }
+            producer_thread.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn loom_try_get_writer_excludes_writers() {
+        model(|| {
+            let my_lock = Arc::new(SeqLock::new(0u8));
+
+            let lock_writer = my_lock.clone();
+            let writer_thread = thread::spawn(move || {
+                if let Some(writer) = lock_writer.try_get_writer() {
+                    writer.update(|item| *item += 1);
+                }
+            });
+            if let Some(writer) = my_lock.try_get_writer() {
+                writer.update(|item| *item += 1);
+            }
+            writer_thread.join().unwrap();
+
+            // every successful writer bumped both the item and the version exactly once
+            let (value, version) = my_lock.get_reader().read_versioned();
+            assert_eq!(value as u64, version);
+            assert_eq!(my_lock.iteration.load(Ordering::Relaxed), 2 * version as usize);
+        });
+    }
+}
@@ -0,0 +1,203 @@
+use crate::sync;
+use crate::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use crate::sync::atomic::fence;
+#[cfg(feature = "async")]
+use std::sync::Mutex;
+#[cfg(feature = "async")]
+use std::task::Waker;
+
+// exponential spin, then yield to the scheduler
+pub(crate) struct Backoff {
+    step: u32,
+}
+
+impl Backoff {
+    const SPIN_LIMIT: u32 = 6;
+
+    pub(crate) fn new() -> Backoff {
+        Backoff { step: 0 }
+    }
+
+    pub(crate) fn snooze(&mut self) {
+        if self.step <= Self::SPIN_LIMIT {
+            for _ in 0..(1 << self.step) {
+                sync::spin_loop();
+            }
+            self.step += 1;
+        } else {
+            sync::yield_now();
+        }
+    }
+}
+
+/* Readers sleep on `epoch`, writers bump it and wake everyone, but only if somebody is parked,
+ * so the writer hot path pays a single load when nobody waits.
+ * Lost wakeups are avoided Dekker-style: the reader announces itself in `waiters` before re-checking
+ * the counter, the writer increments the counter before checking `waiters`, all SeqCst.
+ */
+pub(crate) struct Parking {
+    waiters: AtomicU32,
+    epoch: AtomicU32,
+    #[cfg(feature = "async")]
+    wakers: WakerRegistry,
+}
+
+impl Parking {
+    pub(crate) fn new() -> Parking {
+        Parking {
+            waiters: AtomicU32::new(0),
+            epoch: AtomicU32::new(0),
+            #[cfg(feature = "async")]
+            wakers: WakerRegistry::new(),
+        }
+    }
+
+    // sleeps while the counter still equals `observed`, may return spuriously
+    pub(crate) fn park(&self, iteration: &AtomicUsize, observed: usize, timeout: Option<Duration>) {
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        if iteration.load(Ordering::SeqCst) == observed {
+            futex::wait(&self.epoch, epoch, timeout);
+        }
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    pub(crate) fn unpark_all(&self) {
+        if self.waiters.load(Ordering::SeqCst) > 0 {
+            self.epoch.fetch_add(1, Ordering::SeqCst);
+            futex::wake_all(&self.epoch);
+        }
+        #[cfg(feature = "async")]
+        self.wakers.wake_all();
+    }
+
+    // the caller must re-check the counter afterwards, a write that ended before this call won't wake it
+    #[cfg(feature = "async")]
+    pub(crate) fn register(&self, waker: &Waker) {
+        self.wakers.register(waker);
+    }
+}
+
+/* Async counterpart of the futex: tasks leave their waker here and the next _end_write wakes them all.
+ * Same Dekker pairing as `waiters`, `registered` mirrors the vector length so writers skip the mutex
+ * when nobody subscribed. A woken task registers again on its next poll.
+ */
+#[cfg(feature = "async")]
+struct WakerRegistry {
+    registered: AtomicU32,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+#[cfg(feature = "async")]
+impl WakerRegistry {
+    fn new() -> WakerRegistry {
+        WakerRegistry {
+            registered: AtomicU32::new(0),
+            wakers: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        {
+            let mut wakers = self.wakers.lock().unwrap();
+            if !wakers.iter().any(|w| w.will_wake(waker)) {
+                wakers.push(waker.clone());
+            }
+            self.registered.store(wakers.len() as u32, Ordering::SeqCst);
+        }
+        fence(Ordering::SeqCst);
+    }
+
+    fn wake_all(&self) {
+        if self.registered.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        let wakers = {
+            let mut wakers = self.wakers.lock().unwrap();
+            self.registered.store(0, Ordering::SeqCst);
+            std::mem::take(&mut *wakers)
+        };
+        wakers.into_iter().for_each(Waker::wake);
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(target_arch = "x86_64", target_arch = "aarch64"),
+    not(loom)
+))]
+mod futex {
+    use std::os::raw::{c_int, c_long};
+    use std::sync::atomic::AtomicU32;
+    use std::time::Duration;
+
+    #[cfg(target_arch = "x86_64")]
+    const SYS_FUTEX: c_long = 202;
+    #[cfg(target_arch = "aarch64")]
+    const SYS_FUTEX: c_long = 98;
+    const FUTEX_WAIT_PRIVATE: c_int = 128;
+    const FUTEX_WAKE_PRIVATE: c_int = 129;
+
+    #[repr(C)]
+    struct Timespec {
+        tv_sec: c_long,
+        tv_nsec: c_long,
+    }
+
+    extern "C" {
+        fn syscall(num: c_long, ...) -> c_long;
+    }
+
+    pub fn wait(word: &AtomicU32, expected: u32, timeout: Option<Duration>) {
+        let ts = timeout.map(|t| Timespec {
+            tv_sec: t.as_secs().min(c_long::MAX as u64) as c_long,
+            tv_nsec: t.subsec_nanos() as c_long,
+        });
+        let ts_ptr = ts.as_ref().map_or(std::ptr::null(), |ts| ts as *const Timespec);
+        // EAGAIN (word already changed), EINTR and ETIMEDOUT all just return to the caller's loop
+        unsafe {
+            syscall(SYS_FUTEX, word.as_ptr(), FUTEX_WAIT_PRIVATE, expected, ts_ptr);
+        }
+    }
+
+    pub fn wake_all(word: &AtomicU32) {
+        unsafe {
+            syscall(SYS_FUTEX, word.as_ptr(), FUTEX_WAKE_PRIVATE, c_int::MAX);
+        }
+    }
+}
+
+// no futex, park degrades to a short sleep
+#[cfg(all(
+    not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))),
+    not(loom)
+))]
+mod futex {
+    use std::sync::atomic::AtomicU32;
+    use std::time::Duration;
+
+    const NAP: Duration = Duration::from_micros(50);
+
+    pub fn wait(_word: &AtomicU32, _expected: u32, timeout: Option<Duration>) {
+        std::thread::sleep(timeout.map_or(NAP, |t| t.min(NAP)));
+    }
+
+    pub fn wake_all(_word: &AtomicU32) {}
+}
+
+// loom schedules its own threads, a parked reader just gives way to the others
+#[cfg(loom)]
+mod futex {
+    use crate::sync::atomic::AtomicU32;
+    use std::time::Duration;
+
+    pub fn wait(_word: &AtomicU32, _expected: u32, _timeout: Option<Duration>) {
+        loom::thread::yield_now();
+    }
+
+    pub fn wake_all(_word: &AtomicU32) {}
+}
@@ -0,0 +1,139 @@
+use crate::SeqLock;
+
+use crate::sync;
+use crate::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use std::mem::MaybeUninit;
+
+/* Single-producer broadcast ring of N seqlocked slots ("seqlock queue").
+ * Item with sequence number `seq` lives in slot `seq % N` and is that slot's `seq / N + 1`-th write,
+ * so once published the slot counter equals `2 * (seq / N + 1)`. A reader expecting `seq` compares
+ * the counter it read against that value: smaller means not published yet, larger means the producer
+ * lapped the reader and the item is gone.
+ */
+pub struct SeqLockRing<T, const N: usize> {
+    slots: Box<[SeqLock<T>]>, // N of them, on the heap, a ring of large items doesn't fit the stack
+    published: AtomicUsize, // number of items published so far
+    producer_taken: AtomicBool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RingError {
+    Empty,
+    Lagged(usize), // number of items overwritten before the reader got to them
+}
+
+impl<T: Copy, const N: usize> SeqLockRing<T, N> {
+    pub fn new(val: T) -> SeqLockRing<T, N> {
+        assert!(N > 0, "ring needs at least one slot");
+        SeqLockRing {
+            slots: (0..N).map(|_| SeqLock::new(val)).collect(),
+            published: AtomicUsize::new(0),
+            producer_taken: AtomicBool::new(false),
+        }
+    }
+
+    pub fn get_producer(&self) -> SeqLockRingProducer<'_, T, N> {
+        let taken = self.producer_taken.swap(true, Ordering::Acquire);
+        assert!(!taken, "single producer allowed");
+        SeqLockRingProducer { ring: self }
+    }
+
+    // starts at the next item to be published
+    pub fn get_consumer(&self) -> SeqLockRingConsumer<'_, T, N> {
+        SeqLockRingConsumer {
+            ring: self,
+            cursor: self.published.load(Ordering::Acquire),
+        }
+    }
+
+    // the slot after the last published one may be overwritten by the next push at any moment
+    fn _oldest_available(&self) -> usize {
+        self.published.load(Ordering::Acquire).saturating_sub(N - 1)
+    }
+}
+
+pub struct SeqLockRingProducer<'a, T: Copy, const N: usize> {
+    ring: &'a SeqLockRing<T, N>,
+}
+
+impl<T: Copy, const N: usize> SeqLockRingProducer<'_, T, N> {
+    pub fn push(&mut self, val: T) {
+        let seq = self.ring.published.load(Ordering::Relaxed);
+        self.ring.slots[seq % N].get_writer().write(val);
+        self.ring.published.store(seq + 1, Ordering::Release);
+    }
+}
+
+impl<T: Copy, const N: usize> Drop for SeqLockRingProducer<'_, T, N> {
+    fn drop(&mut self) {
+        self.ring.producer_taken.store(false, Ordering::Release);
+    }
+}
+
+pub struct SeqLockRingConsumer<'a, T: Copy, const N: usize> {
+    ring: &'a SeqLockRing<T, N>,
+    cursor: usize, // sequence number of the next item to read
+}
+
+impl<T: Copy, const N: usize> SeqLockRingConsumer<'_, T, N> {
+    // yields the thread until the next item is published, busy-waits without parking
+    pub fn read_next(&mut self) -> Result<T, RingError> {
+        loop {
+            match self.try_read_next() {
+                Err(RingError::Empty) => sync::yield_now(),
+                result => return result,
+            }
+        }
+    }
+
+    // cursor is left untouched on error, call resync() after Lagged
+    pub fn try_read_next(&mut self) -> Result<T, RingError> {
+        let slot = &self.ring.slots[self.cursor % N];
+        let expected = 2 * (self.cursor / N + 1);
+        let reader = slot.get_reader();
+        let mut val: MaybeUninit<T> = MaybeUninit::uninit();
+        loop {
+            let seq = match reader._try_read_at(val.as_mut_ptr()) {
+                Some(seq) => seq,
+                None => {
+                    // an odd counter is one behind the value the writer will publish, an even one
+                    // means a write ended during the copy
+                    let counter = slot.iteration.load(Ordering::Acquire);
+                    let seq = counter + (counter & 1);
+                    // the expected item is being or was just published, read again
+                    if seq == expected {
+                        sync::spin_loop();
+                        continue;
+                    }
+                    seq
+                }
+            };
+            if seq < expected {
+                return Err(RingError::Empty);
+            }
+            if seq > expected {
+                return Err(RingError::Lagged(self.lag().max(1)));
+            }
+            self.cursor += 1;
+            return Ok(unsafe { val.assume_init() });
+        }
+    }
+
+    // number of items the reader fell behind the oldest one safe to read
+    pub fn lag(&self) -> usize {
+        self.ring._oldest_available().saturating_sub(self.cursor)
+    }
+
+    // jumps to the oldest item safe to read, returns number of skipped items
+    pub fn resync(&mut self) -> usize {
+        let oldest = self.ring._oldest_available();
+        let skipped = oldest.saturating_sub(self.cursor);
+        self.cursor = self.cursor.max(oldest);
+        skipped
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+}
@@ -0,0 +1,12 @@
+// shared by the unit tests and tests/derive.rs (included with #[path])
+
+// brute force runs: SEQLOCK_TEST_ITERATIONS=100000000 cargo test --release
+pub(crate) fn stress_iterations(default: u64) -> u64 {
+    if cfg!(miri) {
+        return 100;
+    }
+    std::env::var("SEQLOCK_TEST_ITERATIONS")
+        .ok()
+        .and_then(|val| val.parse().ok())
+        .unwrap_or(default)
+}
@@ -0,0 +1,138 @@
+use crate::sync::atomic::Ordering;
+use crate::SeqLockReader;
+
+use futures_core::Stream;
+
+use std::future::Future;
+use std::mem::MaybeUninit;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+impl<'a, T: Copy> SeqLockReader<'a, T> {
+    /* Resolves once a version different from `last_version` is published, like read_changed()
+     * without blocking the thread. Readers that never subscribe don't touch the waker registry.
+     */
+    pub fn changed(&self, last_version: u64) -> Changed<'a, '_, T> {
+        Changed {
+            reader: self,
+            last_version,
+        }
+    }
+
+    // current value first, then every newly published version. Versions written in between polls are skipped
+    pub fn snapshots(self) -> Snapshots<'a, T> {
+        Snapshots {
+            reader: self,
+            last_version: None,
+        }
+    }
+
+    fn _poll_changed(&self, last_version: Option<u64>, cx: &mut Context<'_>) -> Poll<(T, u64)> {
+        if let Some(changed) = self._try_read_changed(last_version) {
+            return Poll::Ready(changed);
+        }
+        self.parking.register(cx.waker());
+        match self._try_read_changed(last_version) {
+            Some(changed) => Poll::Ready(changed),
+            None => Poll::Pending,
+        }
+    }
+
+    // None while the version is unchanged or a writer holds the lock, its _end_write wakes us
+    fn _try_read_changed(&self, last_version: Option<u64>) -> Option<(T, u64)> {
+        let mut val: MaybeUninit<T> = MaybeUninit::uninit();
+        loop {
+            match self._try_read_versioned(val.as_mut_ptr()) {
+                Some((version, _)) if Some(version) != last_version => {
+                    return Some((unsafe { val.assume_init() }, version))
+                }
+                Some(_) => return None,
+                // torn read of a write that has already ended, nobody is going to wake us for it
+                None if self.iteration.load(Ordering::SeqCst) & 1 == 0 => continue,
+                None => return None,
+            }
+        }
+    }
+}
+
+pub struct Changed<'a, 'r, T> {
+    reader: &'r SeqLockReader<'a, T>,
+    last_version: u64,
+}
+
+impl<T: Copy> Future for Changed<'_, '_, T> {
+    type Output = (T, u64);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<(T, u64)> {
+        self.reader._poll_changed(Some(self.last_version), cx)
+    }
+}
+
+pub struct Snapshots<'a, T> {
+    reader: SeqLockReader<'a, T>,
+    last_version: Option<u64>,
+}
+
+impl<T: Copy> Stream for Snapshots<'_, T> {
+    type Item = (T, u64);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<(T, u64)>> {
+        let (val, version) = std::task::ready!(self.reader._poll_changed(self.last_version, cx));
+        self.last_version = Some(version);
+        Poll::Ready(Some((val, version)))
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::SeqLock;
+    use futures::executor::block_on;
+    use futures::StreamExt;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_changed_resolves_on_write() {
+        let my_lock = Arc::new(SeqLock::new(0u64));
+
+        let lock_writer = my_lock.clone();
+        let writer_thread = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            lock_writer.get_writer().write(1);
+        });
+
+        let reader = my_lock.get_reader();
+        assert_eq!(block_on(reader.changed(0)), (1, 1));
+        writer_thread.join().unwrap();
+
+        // already changed, ready on first poll
+        my_lock.get_writer().write_versioned(2, 100);
+        assert_eq!(block_on(reader.changed(1)), (2, 100));
+    }
+
+    #[test]
+    fn test_snapshots_stream() {
+        let my_lock = Arc::new(SeqLock::new(0u64));
+        let (sender, receiver) = std::sync::mpsc::channel::<()>();
+
+        let lock_writer = my_lock.clone();
+        let writer_thread = thread::spawn(move || {
+            for i in 1..=3 {
+                receiver.recv().unwrap();
+                lock_writer.get_writer().write(i);
+            }
+        });
+
+        let mut snapshots = my_lock.get_reader().snapshots();
+        let mut seen = Vec::new();
+        block_on(async {
+            while seen.len() < 4 {
+                seen.push(snapshots.next().await.unwrap());
+                let _ = sender.send(());
+            }
+        });
+        writer_thread.join().unwrap();
+        assert_eq!(seen, vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+    }
+}
@@ -0,0 +1,115 @@
+/* Everything threads share goes through here, so the loom and Miri builds can swap it out.
+ *
+ * Normal builds copy the item with plain loads and stores. That is what seqlocks do in practice and a torn
+ * copy is thrown away, but formally it is a data race. Under Miri and loom the item is an array of relaxed
+ * atomic bytes instead, which keeps the protocol checkable: the counter accesses and fences around the copy
+ * are the same in both builds. The atomic build requires T without padding bytes.
+ */
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic;
+#[cfg(not(loom))]
+pub(crate) use std::sync::atomic;
+
+#[cfg(loom)]
+pub(crate) use loom::{hint::spin_loop, thread::yield_now};
+#[cfg(not(loom))]
+pub(crate) use std::{hint::spin_loop, thread::yield_now};
+
+pub(crate) use item::ItemCell;
+
+#[cfg(not(any(miri, loom)))]
+mod item {
+    use std::cell::UnsafeCell;
+    use std::ptr;
+
+    pub(crate) struct ItemCell<T>(UnsafeCell<T>);
+
+    impl<T: Copy> ItemCell<T> {
+        pub(crate) fn new(val: T) -> ItemCell<T> {
+            ItemCell(UnsafeCell::new(val))
+        }
+
+        // optimistic copy, only valid if the counter didn't move meanwhile
+        pub(crate) unsafe fn read_into(&self, dst: *mut T) {
+            *dst = *self.0.get();
+            // some people use 'std::ptr::read_volatile' here, the fences around the copy are what matters
+        }
+
+        // optimistic copy of `len` bytes at offset `start`
+        pub(crate) unsafe fn read_bytes_into(&self, dst: *mut T, start: usize, len: usize) {
+            let src = self.0.get() as *const u8;
+            ptr::copy_nonoverlapping(src.add(start), (dst as *mut u8).add(start), len);
+        }
+
+        // optimistic projection, `project` must only read through the pointer
+        pub(crate) unsafe fn project<R>(&self, project: impl FnOnce(*const T) -> R) -> R {
+            project(self.0.get())
+        }
+
+        // writer holds the lock
+        pub(crate) unsafe fn write(&self, val: T) {
+            *self.0.get() = val;
+        }
+
+        // writer holds the lock
+        pub(crate) unsafe fn with_mut<R>(&self, closure: impl FnOnce(*mut T) -> R) -> R {
+            closure(self.0.get())
+        }
+    }
+}
+
+#[cfg(any(miri, loom))]
+mod item {
+    use super::atomic::{AtomicU8, Ordering};
+    use std::marker::PhantomData;
+    use std::mem::{size_of, MaybeUninit};
+
+    pub(crate) struct ItemCell<T> {
+        bytes: Box<[AtomicU8]>,
+        _item: PhantomData<T>,
+    }
+
+    impl<T: Copy> ItemCell<T> {
+        pub(crate) fn new(val: T) -> ItemCell<T> {
+            let src = &val as *const T as *const u8;
+            ItemCell {
+                bytes: (0..size_of::<T>())
+                    .map(|i| AtomicU8::new(unsafe { *src.add(i) }))
+                    .collect(),
+                _item: PhantomData,
+            }
+        }
+
+        pub(crate) unsafe fn read_into(&self, dst: *mut T) {
+            self.read_bytes_into(dst, 0, size_of::<T>());
+        }
+
+        pub(crate) unsafe fn read_bytes_into(&self, dst: *mut T, start: usize, len: usize) {
+            let dst = dst as *mut u8;
+            for i in start..start + len {
+                *dst.add(i) = self.bytes[i].load(Ordering::Relaxed);
+            }
+        }
+
+        pub(crate) unsafe fn project<R>(&self, project: impl FnOnce(*const T) -> R) -> R {
+            let mut copy = MaybeUninit::<T>::uninit();
+            self.read_into(copy.as_mut_ptr());
+            project(copy.as_ptr())
+        }
+
+        pub(crate) unsafe fn write(&self, val: T) {
+            let src = &val as *const T as *const u8;
+            for (i, byte) in self.bytes.iter().enumerate() {
+                byte.store(*src.add(i), Ordering::Relaxed);
+            }
+        }
+
+        pub(crate) unsafe fn with_mut<R>(&self, closure: impl FnOnce(*mut T) -> R) -> R {
+            let mut copy = MaybeUninit::<T>::uninit();
+            self.read_into(copy.as_mut_ptr());
+            let result = closure(copy.as_mut_ptr());
+            self.write(copy.assume_init());
+            result
+        }
+    }
+}
@@ -0,0 +1,62 @@
+#![cfg(not(loom))]
+
+#[path = "../src/stress.rs"]
+mod stress;
+
+use seqlock_derive::SeqLockFields;
+use stress::stress_iterations;
+use std::sync::Arc;
+use std::thread;
+
+#[derive(Clone, Copy, SeqLockFields)]
+struct Quote {
+    bid: u64,
+    ask: u64,
+    levels: [u32; 4],
+}
+
+#[test]
+fn test_field_getters_and_setters() {
+    let quote = QuoteSeqLock::new(Quote {
+        bid: 1,
+        ask: 2,
+        levels: [0; 4],
+    });
+    assert_eq!((quote.bid(), quote.ask()), (1, 2));
+
+    quote.set_ask(5);
+    quote.set_levels([1, 2, 3, 4]);
+    assert_eq!(quote.ask(), 5);
+    assert_eq!(quote.levels(), [1, 2, 3, 4]);
+    assert_eq!(quote.lock().get_reader().version(), 2);
+
+    let whole = quote.read();
+    assert_eq!((whole.bid, whole.ask), (1, 5));
+}
+
+#[test]
+fn test_read_group_is_consistent() {
+    let quote = Arc::new(QuoteSeqLock::new(Quote {
+        bid: 0,
+        ask: 1,
+        levels: [0; 4],
+    }));
+    let iterations = stress_iterations(1000000);
+
+    let writer = quote.clone();
+    let writer_thread = thread::spawn(move || {
+        for i in 0..iterations {
+            writer.write(Quote {
+                bid: i,
+                ask: i + 1,
+                levels: [i as u32; 4],
+            });
+        }
+    });
+
+    for _ in 0..iterations {
+        let (bid, ask) = quote.read_group(|q| (q.bid(), q.ask()));
+        assert_eq!(bid + 1, ask);
+    }
+    writer_thread.join().unwrap();
+}
@@ -0,0 +1,11 @@
+CREATE TABLE employees
+(
+    id SERIAL PRIMARY KEY,
+    first_name VARCHAR NOT NULL,
+    last_name VARCHAR NOT NULL,
+    department VARCHAR NOT NULL,
+    salary INTEGER NOT NULL,
+    age INTEGER NOT NULL
+);
+
+INSERT INTO employees VALUES (0, 'Mr', 'Admin', 'Dept', 3000, 30);
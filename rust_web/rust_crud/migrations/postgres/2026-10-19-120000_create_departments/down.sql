@@ -0,0 +1,5 @@
+ALTER TABLE employees ADD COLUMN department VARCHAR;
+UPDATE employees e SET department = d.name FROM departments d WHERE d.id = e.department_id;
+ALTER TABLE employees ALTER COLUMN department SET NOT NULL;
+ALTER TABLE employees DROP COLUMN department_id;
+DROP TABLE departments;
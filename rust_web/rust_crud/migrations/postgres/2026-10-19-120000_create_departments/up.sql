@@ -0,0 +1,24 @@
+CREATE TABLE departments
+(
+    id SERIAL PRIMARY KEY,
+    name VARCHAR NOT NULL
+);
+
+-- unique ignoring case, like COLLATE NOCASE on sqlite
+CREATE UNIQUE INDEX departments_name ON departments (lower(name));
+
+-- "Eng" and "eng " end up in the same department, the first spelling wins
+INSERT INTO departments (name)
+SELECT name FROM (
+    SELECT DISTINCT ON (lower(trim(department))) trim(department) AS name, id
+    FROM employees
+    ORDER BY lower(trim(department)), id
+) first_spelling
+ORDER BY id;
+
+ALTER TABLE employees ADD COLUMN department_id INTEGER REFERENCES departments (id);
+UPDATE employees e SET department_id = d.id
+FROM departments d WHERE lower(d.name) = lower(trim(e.department));
+ALTER TABLE employees ALTER COLUMN department_id SET NOT NULL;
+ALTER TABLE employees DROP COLUMN department;
+CREATE INDEX employees_department_id ON employees (department_id);
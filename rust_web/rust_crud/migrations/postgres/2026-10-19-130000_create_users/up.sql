@@ -0,0 +1,9 @@
+CREATE TABLE users
+(
+    id SERIAL PRIMARY KEY,
+    username VARCHAR NOT NULL,
+    password_hash VARCHAR NOT NULL,
+    role VARCHAR NOT NULL CHECK (role IN ('viewer', 'hr_editor', 'admin'))
+);
+
+CREATE UNIQUE INDEX users_username ON users (lower(username));
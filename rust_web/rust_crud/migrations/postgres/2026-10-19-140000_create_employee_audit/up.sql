@@ -0,0 +1,12 @@
+-- no foreign key, the history outlives deleted employees
+CREATE TABLE employee_audit
+(
+    id SERIAL PRIMARY KEY,
+    employee_id INTEGER NOT NULL,
+    actor VARCHAR NOT NULL,
+    changed_at VARCHAR NOT NULL DEFAULT (to_char(now() AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.MS"Z"')),
+    operation VARCHAR NOT NULL CHECK (operation IN ('create', 'update', 'delete')),
+    changes TEXT NOT NULL
+);
+
+CREATE INDEX employee_audit_employee_id ON employee_audit (employee_id);
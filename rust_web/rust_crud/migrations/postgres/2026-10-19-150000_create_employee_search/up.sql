@@ -0,0 +1,5 @@
+-- kept in sync by postgres itself, the 'simple' config neither stems nor drops stop words
+ALTER TABLE employees ADD COLUMN search tsvector
+    GENERATED ALWAYS AS (to_tsvector('simple', first_name || ' ' || last_name)) STORED;
+
+CREATE INDEX employees_search ON employees USING gin (search);
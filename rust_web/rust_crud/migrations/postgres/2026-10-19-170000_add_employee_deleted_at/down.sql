@@ -0,0 +1,8 @@
+DELETE FROM employees WHERE deleted_at IS NOT NULL;
+ALTER TABLE employees DROP COLUMN deleted_at;
+
+UPDATE employee_audit SET operation = 'update' WHERE operation = 'restore';
+UPDATE employee_audit SET operation = 'delete' WHERE operation = 'purge';
+ALTER TABLE employee_audit DROP CONSTRAINT employee_audit_operation_check;
+ALTER TABLE employee_audit ADD CONSTRAINT employee_audit_operation_check
+    CHECK (operation IN ('create', 'update', 'delete'));
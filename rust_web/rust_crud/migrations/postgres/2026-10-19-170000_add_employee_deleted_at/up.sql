@@ -0,0 +1,8 @@
+-- set by DELETE /employees/{id}, the row is purged once it is older than the retention period
+ALTER TABLE employees ADD COLUMN deleted_at VARCHAR;
+
+CREATE INDEX employees_deleted_at ON employees (deleted_at);
+
+ALTER TABLE employee_audit DROP CONSTRAINT employee_audit_operation_check;
+ALTER TABLE employee_audit ADD CONSTRAINT employee_audit_operation_check
+    CHECK (operation IN ('create', 'update', 'delete', 'restore', 'purge'));
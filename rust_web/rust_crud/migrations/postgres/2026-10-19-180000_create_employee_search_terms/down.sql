@@ -0,0 +1,3 @@
+DROP TRIGGER employee_search_terms_sync ON employees;
+DROP FUNCTION employee_search_terms_sync();
+DROP TABLE employee_search_terms;
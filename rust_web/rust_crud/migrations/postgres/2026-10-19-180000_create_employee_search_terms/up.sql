@@ -0,0 +1,29 @@
+-- every distinct indexed word and how many employees have it, what fts5vocab gives sqlite,
+-- so a search reads the words it may need instead of running ts_stat over every row
+CREATE TABLE employee_search_terms (
+    term TEXT COLLATE "C" PRIMARY KEY,
+    doc INTEGER NOT NULL
+);
+
+INSERT INTO employee_search_terms (term, doc)
+SELECT word, ndoc FROM ts_stat('SELECT search FROM employees');
+
+CREATE FUNCTION employee_search_terms_sync() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP IN ('UPDATE', 'DELETE') THEN
+        UPDATE employee_search_terms SET doc = doc - 1
+        WHERE term = ANY (tsvector_to_array(OLD.search));
+        DELETE FROM employee_search_terms
+        WHERE term = ANY (tsvector_to_array(OLD.search)) AND doc = 0;
+    END IF;
+    IF TG_OP IN ('INSERT', 'UPDATE') THEN
+        INSERT INTO employee_search_terms (term, doc)
+        SELECT word, 1 FROM unnest(tsvector_to_array(NEW.search)) AS word
+        ON CONFLICT (term) DO UPDATE SET doc = employee_search_terms.doc + 1;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER employee_search_terms_sync AFTER INSERT OR DELETE OR UPDATE OF first_name, last_name
+ON employees FOR EACH ROW EXECUTE FUNCTION employee_search_terms_sync();
@@ -0,0 +1,17 @@
+CREATE TABLE employees_old
+(
+    id Integer PRIMARY KEY NOT NULL,
+    first_name VARCHAR NOT NULL,
+    last_name VARCHAR NOT NULL,
+    department VARCHAR NOT NULL,
+    salary INT NOT NULL,
+    age INT NOT NULL
+);
+
+INSERT INTO employees_old (id, first_name, last_name, department, salary, age)
+SELECT e.id, e.first_name, e.last_name, d.name, e.salary, e.age
+FROM employees e JOIN departments d ON d.id = e.department_id;
+
+DROP TABLE employees;
+ALTER TABLE employees_old RENAME TO employees;
+DROP TABLE departments;
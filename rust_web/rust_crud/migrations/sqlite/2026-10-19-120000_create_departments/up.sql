@@ -0,0 +1,27 @@
+CREATE TABLE departments
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    name VARCHAR NOT NULL UNIQUE COLLATE NOCASE
+);
+
+-- "Eng" and "eng " end up in the same department, the first spelling wins
+INSERT OR IGNORE INTO departments (name)
+SELECT trim(department) FROM employees GROUP BY trim(department) ORDER BY min(id);
+
+CREATE TABLE employees_new
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    first_name VARCHAR NOT NULL,
+    last_name VARCHAR NOT NULL,
+    department_id INTEGER NOT NULL REFERENCES departments (id),
+    salary INT NOT NULL,
+    age INT NOT NULL
+);
+
+INSERT INTO employees_new (id, first_name, last_name, department_id, salary, age)
+SELECT e.id, e.first_name, e.last_name, d.id, e.salary, e.age
+FROM employees e JOIN departments d ON d.name = trim(e.department);
+
+DROP TABLE employees;
+ALTER TABLE employees_new RENAME TO employees;
+CREATE INDEX employees_department_id ON employees (department_id);
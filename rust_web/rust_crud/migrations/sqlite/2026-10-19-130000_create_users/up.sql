@@ -0,0 +1,7 @@
+CREATE TABLE users
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    username VARCHAR NOT NULL UNIQUE COLLATE NOCASE,
+    password_hash VARCHAR NOT NULL,
+    role VARCHAR NOT NULL CHECK (role IN ('viewer', 'hr_editor', 'admin'))
+);
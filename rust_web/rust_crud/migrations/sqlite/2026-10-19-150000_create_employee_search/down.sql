@@ -0,0 +1,5 @@
+DROP TRIGGER employee_search_update;
+DROP TRIGGER employee_search_delete;
+DROP TRIGGER employee_search_insert;
+DROP TABLE employee_search_terms;
+DROP TABLE employee_search;
@@ -0,0 +1,30 @@
+-- full-text index over the names, employees stays the only copy of the data
+CREATE VIRTUAL TABLE employee_search USING fts5
+(
+    first_name,
+    last_name,
+    content = 'employees',
+    content_rowid = 'id'
+);
+
+INSERT INTO employee_search (employee_search) VALUES ('rebuild');
+
+-- every distinct indexed word, the candidates for typo-tolerant matching
+CREATE VIRTUAL TABLE employee_search_terms USING fts5vocab (employee_search, 'row');
+
+CREATE TRIGGER employee_search_insert AFTER INSERT ON employees BEGIN
+    INSERT INTO employee_search (rowid, first_name, last_name)
+    VALUES (new.id, new.first_name, new.last_name);
+END;
+
+CREATE TRIGGER employee_search_delete AFTER DELETE ON employees BEGIN
+    INSERT INTO employee_search (employee_search, rowid, first_name, last_name)
+    VALUES ('delete', old.id, old.first_name, old.last_name);
+END;
+
+CREATE TRIGGER employee_search_update AFTER UPDATE OF first_name, last_name ON employees BEGIN
+    INSERT INTO employee_search (employee_search, rowid, first_name, last_name)
+    VALUES ('delete', old.id, old.first_name, old.last_name);
+    INSERT INTO employee_search (rowid, first_name, last_name)
+    VALUES (new.id, new.first_name, new.last_name);
+END;
@@ -0,0 +1,2 @@
+-- bumped on every update, the ETag of an employee
+ALTER TABLE employees ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
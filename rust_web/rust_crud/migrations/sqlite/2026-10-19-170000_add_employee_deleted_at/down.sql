@@ -0,0 +1,23 @@
+DELETE FROM employees WHERE deleted_at IS NOT NULL;
+DROP INDEX employees_deleted_at;
+ALTER TABLE employees DROP COLUMN deleted_at;
+
+CREATE TABLE employee_audit_new
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    employee_id INTEGER NOT NULL,
+    actor VARCHAR NOT NULL,
+    changed_at VARCHAR NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    operation VARCHAR NOT NULL CHECK (operation IN ('create', 'update', 'delete')),
+    changes TEXT NOT NULL
+);
+
+INSERT INTO employee_audit_new
+SELECT id, employee_id, actor, changed_at,
+       CASE operation WHEN 'restore' THEN 'update' WHEN 'purge' THEN 'delete' ELSE operation END,
+       changes
+FROM employee_audit;
+DROP TABLE employee_audit;
+ALTER TABLE employee_audit_new RENAME TO employee_audit;
+
+CREATE INDEX employee_audit_employee_id ON employee_audit (employee_id);
@@ -0,0 +1,21 @@
+-- set by DELETE /employees/{id}, the row is purged once it is older than the retention period
+ALTER TABLE employees ADD COLUMN deleted_at VARCHAR;
+
+CREATE INDEX employees_deleted_at ON employees (deleted_at);
+
+-- sqlite can't change a check constraint, the audit table is copied into a new one
+CREATE TABLE employee_audit_new
+(
+    id INTEGER PRIMARY KEY NOT NULL,
+    employee_id INTEGER NOT NULL,
+    actor VARCHAR NOT NULL,
+    changed_at VARCHAR NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    operation VARCHAR NOT NULL CHECK (operation IN ('create', 'update', 'delete', 'restore', 'purge')),
+    changes TEXT NOT NULL
+);
+
+INSERT INTO employee_audit_new SELECT * FROM employee_audit;
+DROP TABLE employee_audit;
+ALTER TABLE employee_audit_new RENAME TO employee_audit;
+
+CREATE INDEX employee_audit_employee_id ON employee_audit (employee_id);
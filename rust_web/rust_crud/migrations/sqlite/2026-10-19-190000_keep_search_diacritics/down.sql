@@ -0,0 +1,14 @@
+DROP TABLE employee_search_terms;
+DROP TABLE employee_search;
+
+CREATE VIRTUAL TABLE employee_search USING fts5
+(
+    first_name,
+    last_name,
+    content = 'employees',
+    content_rowid = 'id'
+);
+
+INSERT INTO employee_search (employee_search) VALUES ('rebuild');
+
+CREATE VIRTUAL TABLE employee_search_terms USING fts5vocab (employee_search, 'row');
@@ -0,0 +1,16 @@
+-- keep diacritics like the 'simple' config of postgres, the search terms are matched as typed
+DROP TABLE employee_search_terms;
+DROP TABLE employee_search;
+
+CREATE VIRTUAL TABLE employee_search USING fts5
+(
+    first_name,
+    last_name,
+    content = 'employees',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 0'
+);
+
+INSERT INTO employee_search (employee_search) VALUES ('rebuild');
+
+CREATE VIRTUAL TABLE employee_search_terms USING fts5vocab (employee_search, 'row');
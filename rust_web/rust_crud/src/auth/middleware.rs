@@ -0,0 +1,84 @@
+use crate::auth::{Auth, Claims};
+use crate::error_handler::CustomError;
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{header, Method};
+use actix_web::{web, Error, HttpMessage};
+use futures::future::{ok, Either, Ready};
+use std::task::{Context, Poll};
+
+// reachable without a token
+const PUBLIC: &[(Method, &str)] = &[
+    (Method::POST, "/login"),
+    (Method::GET, "/openapi.json"),
+    (Method::GET, "/docs"),
+];
+
+// checks the bearer token of every request and hands the claims to the Claims extractor
+pub struct Authentication;
+
+impl<S, B> Transform<S> for Authentication
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthenticationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(AuthenticationMiddleware { service })
+    }
+}
+
+pub struct AuthenticationMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service for AuthenticationMiddleware<S>
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Request = ServiceRequest;
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: ServiceRequest) -> Self::Future {
+        let public = PUBLIC
+            .iter()
+            .any(|(method, path)| req.method() == method && req.path() == *path);
+        if public {
+            return Either::Left(self.service.call(req));
+        }
+        match authenticate(&req) {
+            Ok(claims) => {
+                req.extensions_mut().insert(claims);
+                Either::Left(self.service.call(req))
+            }
+            Err(e) => Either::Right(ok(req.error_response(e))),
+        }
+    }
+}
+
+fn authenticate(req: &ServiceRequest) -> Result<Claims, CustomError> {
+    let auth = req
+        .app_data::<web::Data<Auth>>()
+        .ok_or_else(|| CustomError::new(500, "Authentication is not configured".to_string()))?;
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| CustomError::new(401, "Authentication required".to_string()))?;
+    auth.verify(token.trim())
+}
@@ -0,0 +1,45 @@
+mod middleware;
+mod model;
+mod route;
+
+pub use middleware::Authentication;
+pub use model::*;
+pub use route::{init_routes, AuthApi};
+
+#[cfg(test)]
+pub mod testing {
+    use super::{Auth, Authentication, Role, Users};
+    use crate::db::Pool;
+    use actix_http::Request;
+    use actix_web::dev::{Service, ServiceResponse};
+    use actix_web::{test, App, Error};
+
+    pub fn test_auth() -> Auth {
+        let mut auth = Auth::new(b"test secret");
+        auth.hash_cost = 4;
+        auth
+    }
+
+    // every route like in main, behind the Authentication middleware
+    pub async fn test_app(pool: &Pool) -> impl Service<Request = Request, Response = ServiceResponse, Error = Error> {
+        test::init_service(
+            App::new()
+                .wrap(Authentication)
+                .data(pool.clone())
+                .data(test_auth())
+                .configure(crate::init_routes),
+        )
+        .await
+    }
+
+    // Authorization header value for a user that only exists in the token
+    pub fn bearer(role: Role) -> String {
+        let user = Users {
+            id: 0,
+            username: role.as_str().to_string(),
+            password_hash: String::new(),
+            role: role.as_str().to_string(),
+        };
+        format!("Bearer {}", test_auth().issue(&user).unwrap())
+    }
+}
@@ -0,0 +1,289 @@
+use crate::db::{self, insert_returning, lower, Pool};
+use crate::error_handler::CustomError;
+use crate::schema::users;
+use actix_web::dev::Payload;
+use actix_web::{FromRequest, HttpRequest};
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use futures::future::{ready, Ready};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
+use utoipa::ToSchema;
+use validator::{Validate, ValidationError};
+
+// tokens aren't revoked, a deleted or demoted user keeps their token until it expires
+const TOKEN_TTL_SECS: u64 = 8 * 60 * 60;
+
+// checked against when the username is unknown, so a login takes as long as with a wrong password
+const DUMMY_HASH: &str = "$2b$12$PJ0WTElvxHpuEAENs5Mpb.Dpi2vm871UIIgruaaKaRSn69BJ0q9pS";
+
+// ordered, every role can do what the roles before it can
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Viewer,
+    HrEditor,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::HrEditor => "hr_editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    fn parse(role: &str) -> Result<Role, CustomError> {
+        match role {
+            "viewer" => Ok(Role::Viewer),
+            "hr_editor" => Ok(Role::HrEditor),
+            "admin" => Ok(Role::Admin),
+            _ => Err(CustomError::new(500, format!("Unknown role '{}'", role))),
+        }
+    }
+
+    // fields left out of every response for this role
+    pub fn hidden_fields(self) -> &'static [&'static str] {
+        match self {
+            Role::Viewer => &["salary"],
+            Role::HrEditor | Role::Admin => &[],
+        }
+    }
+}
+
+// JWT payload, also the extractor for the user behind a request
+#[derive(Deserialize, Serialize, Clone, Debug, ToSchema)]
+pub struct Claims {
+    pub sub: i32,
+    pub username: String,
+    pub role: Role,
+    pub exp: u64,
+}
+
+impl Claims {
+    pub fn require(&self, role: Role) -> Result<(), CustomError> {
+        if self.role >= role {
+            Ok(())
+        } else {
+            Err(CustomError::new(403, format!("Requires the {} role", role.as_str())))
+        }
+    }
+
+    pub fn can_see(&self, field: &str) -> bool {
+        !self.role.hidden_fields().contains(&field)
+    }
+
+    // serializes value without the fields hidden from this user
+    pub fn redact<T: Serialize>(&self, value: &T) -> Result<Value, CustomError> {
+        let mut value = serde_json::to_value(value)
+            .map_err(|e| CustomError::new(500, format!("Failed serializing response: {}", e)))?;
+        let hidden = self.role.hidden_fields();
+        if !hidden.is_empty() {
+            strip_fields(&mut value, hidden);
+        }
+        Ok(value)
+    }
+}
+
+fn strip_fields(value: &mut Value, fields: &[&str]) {
+    match value {
+        Value::Object(map) => {
+            for field in fields {
+                map.remove(*field);
+            }
+            for value in map.values_mut() {
+                strip_fields(value, fields);
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                strip_fields(value, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl FromRequest for Claims {
+    type Error = CustomError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    // set by the Authentication middleware
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let claims = req.extensions().get::<Claims>().cloned();
+        ready(claims.ok_or_else(|| CustomError::new(401, "Authentication required".to_string())))
+    }
+}
+
+// signs and checks tokens, hashes passwords
+pub struct Auth {
+    secret: Vec<u8>,
+    pub(crate) hash_cost: u32,
+}
+
+impl Auth {
+    pub fn new(secret: &[u8]) -> Auth {
+        Auth {
+            secret: secret.to_vec(),
+            hash_cost: bcrypt::DEFAULT_COST,
+        }
+    }
+
+    pub fn issue(&self, user: &Users) -> Result<String, CustomError> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let claims = Claims {
+            sub: user.id,
+            username: user.username.clone(),
+            role: Role::parse(&user.role)?,
+            exp: now.as_secs() + TOKEN_TTL_SECS,
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(&self.secret))
+            .map_err(|e| CustomError::new(500, format!("Failed signing token: {}", e)))
+    }
+
+    pub fn verify(&self, token: &str) -> Result<Claims, CustomError> {
+        decode::<Claims>(token, &DecodingKey::from_secret(&self.secret), &Validation::default())
+            .map(|data| data.claims)
+            .map_err(|_| CustomError::new(401, "Invalid or expired token".to_string()))
+    }
+
+    fn hash(&self, password: &str) -> Result<String, CustomError> {
+        bcrypt::hash(password, self.hash_cost)
+            .map_err(|e| CustomError::new(500, format!("Failed hashing password: {}", e)))
+    }
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
+pub struct User {
+    #[validate(length(min = 1, max = 50, message = "must be 1 to 50 characters"))]
+    pub username: String,
+    #[validate(
+        length(min = 8, message = "must be at least 8 characters"),
+        custom(function = "bcrypt_length", message = "must be at most 72 bytes")
+    )]
+    pub password: String,
+    pub role: Role,
+}
+
+// bcrypt ignores everything after the first 72 bytes, a multibyte character takes several
+fn bcrypt_length(password: &str) -> Result<(), ValidationError> {
+    if password.len() > 72 {
+        return Err(ValidationError::new("length"));
+    }
+    Ok(())
+}
+
+#[derive(Insertable)]
+#[table_name = "users"]
+struct NewUser {
+    username: String,
+    password_hash: String,
+    role: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct Login {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Serialize, Queryable, ToSchema)]
+pub struct Users {
+    pub id: i32,
+    pub username: String,
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub role: String,
+}
+
+// body of POST /login
+#[derive(Serialize, ToSchema)]
+pub struct Token {
+    pub token: String,
+    pub token_type: &'static str,
+}
+
+impl Users {
+    pub fn login(pool: &Pool, auth: &Auth, login: Login) -> Result<String, CustomError> {
+        let conn = db::connection(pool)?;
+        let user = users::table
+            .filter(lower(users::username).eq(lower(&login.username)))
+            .first::<Users>(&conn)
+            .optional()?;
+        let valid = match &user {
+            Some(user) => bcrypt::verify(&login.password, &user.password_hash).unwrap_or(false),
+            None => {
+                let _ = bcrypt::verify(&login.password, DUMMY_HASH);
+                false
+            }
+        };
+        match user {
+            Some(user) if valid => auth.issue(&user),
+            _ => Err(CustomError::new(401, "Invalid username or password".to_string())),
+        }
+    }
+
+    pub fn find_all(pool: &Pool) -> Result<Vec<Self>, CustomError> {
+        let conn = db::connection(pool)?;
+        let users = users::table.order(users::id.asc()).load::<Users>(&conn)?;
+        Ok(users)
+    }
+
+    pub fn create(pool: &Pool, auth: &Auth, user: User) -> Result<Self, CustomError> {
+        let user = User {
+            username: user.username.trim().to_string(),
+            ..user
+        };
+        user.validate()?;
+        let new_user = NewUser {
+            username: user.username,
+            password_hash: auth.hash(&user.password)?,
+            role: user.role.as_str().to_string(),
+        };
+        let conn = db::connection(pool)?;
+        let user = insert_returning!(&conn, users::table, new_user)?;
+        Ok(user)
+    }
+
+    // tokens already issued to the user stay valid until they expire, see TOKEN_TTL_SECS
+    pub fn delete(pool: &Pool, id: i32) -> Result<usize, CustomError> {
+        let conn = db::connection(pool)?;
+        let deleted = diesel::delete(users::table.filter(users::id.eq(id))).execute(&conn)?;
+        if deleted == 0 {
+            return Err(DieselError::NotFound.into());
+        }
+        Ok(deleted)
+    }
+
+    // first start, creates "admin" unless an admin already exists
+    pub fn ensure_admin(pool: &Pool, auth: &Auth, password: &str) -> Result<bool, CustomError> {
+        let admins = users::table
+            .filter(users::role.eq(Role::Admin.as_str()))
+            .count()
+            .get_result::<i64>(&db::connection(pool)?)?;
+        if admins > 0 {
+            return Ok(false);
+        }
+        let taken = users::table
+            .filter(lower(users::username).eq("admin"))
+            .count()
+            .get_result::<i64>(&db::connection(pool)?)?;
+        if taken > 0 {
+            warn!("a user named admin exists but isn't an admin, no admin user created");
+            return Ok(false);
+        }
+        let admin = User {
+            username: "admin".to_string(),
+            password: password.to_string(),
+            role: Role::Admin,
+        };
+        Users::create(pool, auth, admin)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,259 @@
+use crate::auth::{Auth, Claims, Login, Role, Token, User, Users};
+use crate::db::{self, Pool};
+use crate::error_handler::{CustomError, ErrorResponse};
+use crate::openapi::Deleted;
+use actix_web::{delete, get, post, web, HttpResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(login, me, find_all, create, delete),
+    components(schemas(Login, Token, Claims, Role, User, Users))
+)]
+pub struct AuthApi;
+
+/// Exchange a username and password for a bearer token
+#[utoipa::path(
+    post, path = "/login", tag = "auth", security(()),
+    request_body = Login,
+    responses(
+        (status = 200, body = Token),
+        (status = 401, description = "Wrong username or password", body = ErrorResponse),
+    )
+)]
+#[post("/login")]
+async fn login(
+    pool: web::Data<Pool>,
+    auth: web::Data<Auth>,
+    login: web::Json<Login>,
+) -> Result<HttpResponse, CustomError> {
+    let token = db::run(&pool, move |pool| Users::login(pool, &auth, login.into_inner())).await?;
+    Ok(HttpResponse::Ok().json(Token { token, token_type: "Bearer" }))
+}
+
+/// The user behind the token
+#[utoipa::path(get, path = "/me", tag = "auth", responses((status = 200, body = Claims)))]
+#[get("/me")]
+async fn me(user: Claims) -> Result<HttpResponse, CustomError> {
+    Ok(HttpResponse::Ok().json(user))
+}
+
+/// All users, admin only
+#[utoipa::path(
+    get, path = "/users", tag = "auth",
+    responses(
+        (status = 200, body = Vec<Users>),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+#[get("/users")]
+async fn find_all(pool: web::Data<Pool>, user: Claims) -> Result<HttpResponse, CustomError> {
+    user.require(Role::Admin)?;
+    let users = db::run(&pool, Users::find_all).await?;
+    Ok(HttpResponse::Ok().json(users))
+}
+
+/// Create a user, admin only
+#[utoipa::path(
+    post, path = "/users", tag = "auth",
+    request_body = User,
+    responses(
+        (status = 200, body = Users),
+        (status = 403, body = ErrorResponse),
+        (status = 409, description = "The username is taken", body = ErrorResponse),
+        (status = 422, body = ErrorResponse),
+    )
+)]
+#[post("/users")]
+async fn create(
+    pool: web::Data<Pool>,
+    auth: web::Data<Auth>,
+    user: Claims,
+    new_user: web::Json<User>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::Admin)?;
+    let created = db::run(&pool, move |pool| {
+        Users::create(pool, &auth, new_user.into_inner())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(created))
+}
+
+/// Delete a user, admin only
+#[utoipa::path(
+    delete, path = "/users/{id}", tag = "auth",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Deleted),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+#[delete("/users/{id}")]
+async fn delete(
+    pool: web::Data<Pool>,
+    user: Claims,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::Admin)?;
+    let deleted = db::run(&pool, move |pool| Users::delete(pool, id.into_inner())).await?;
+    Ok(HttpResponse::Ok().json(Deleted { deleted }))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(login);
+    config.service(me);
+    config.service(find_all);
+    config.service(create);
+    config.service(delete);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::testing::{bearer, test_app, test_auth};
+    use crate::db;
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    #[actix_rt::test]
+    async fn test_login_and_me() {
+        let pool = db::test_pool();
+        Users::ensure_admin(&pool, &test_auth(), "correct horse").unwrap();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::post()
+            .uri("/login")
+            .set_json(&json!({ "username": "admin", "password": "wrong horse" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::post()
+            .uri("/login")
+            .set_json(&json!({ "username": "admin", "password": "correct horse" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        let token = body["token"].as_str().unwrap();
+
+        let req = test::TestRequest::get()
+            .uri("/me")
+            .header("Authorization", format!("Bearer {}", token))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let profile: Value = test::read_body_json(resp).await;
+        assert_eq!(profile["username"], json!("admin"));
+        assert_eq!(profile["role"], json!("admin"));
+
+        let req = test::TestRequest::post()
+            .uri("/login")
+            .set_json(&json!({ "username": "nobody", "password": "correct horse" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_ensure_admin_skips_taken_name() {
+        let pool = db::test_pool();
+        let viewer = User {
+            username: "Admin".to_string(),
+            password: "12345678".to_string(),
+            role: Role::Viewer,
+        };
+        Users::create(&pool, &test_auth(), viewer).unwrap();
+        assert!(!Users::ensure_admin(&pool, &test_auth(), "correct horse").unwrap());
+        assert_eq!(Users::find_all(&pool).unwrap().len(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn test_missing_or_bad_token_is_401() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get().uri("/me").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get()
+            .uri("/me")
+            .header("Authorization", "Bearer not.a.token")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        // signed with another secret
+        let forged = Auth::new(b"another secret")
+            .issue(&Users {
+                id: 1,
+                username: "mallory".to_string(),
+                password_hash: String::new(),
+                role: "admin".to_string(),
+            })
+            .unwrap();
+        let req = test::TestRequest::get()
+            .uri("/me")
+            .header("Authorization", format!("Bearer {}", forged))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_user_management_requires_admin() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        let new_user = json!({ "username": "hr", "password": "12345678", "role": "hr_editor" });
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .header("Authorization", bearer(Role::HrEditor))
+            .set_json(&new_user)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .header("Authorization", bearer(Role::Admin))
+            .set_json(&new_user)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let created: Value = test::read_body_json(resp).await;
+        assert_eq!(created["role"], json!("hr_editor"));
+        assert!(created.get("password_hash").is_none());
+
+        let req = test::TestRequest::post()
+            .uri("/users")
+            .header("Authorization", bearer(Role::Admin))
+            .set_json(&json!({ "username": "short", "password": "1234", "role": "viewer" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        for invalid in [
+            json!({ "username": "   ", "password": "12345678", "role": "viewer" }),
+            // 40 characters, 80 bytes
+            json!({ "username": "long", "password": "é".repeat(40), "role": "viewer" }),
+        ] {
+            let req = test::TestRequest::post()
+                .uri("/users")
+                .header("Authorization", bearer(Role::Admin))
+                .set_json(&invalid)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let req = test::TestRequest::delete()
+            .uri(&format!("/users/{}", created["id"]))
+            .header("Authorization", bearer(Role::Admin))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,207 @@
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+const DEFAULT_BIND: &str = "127.0.0.1:8000";
+const DEFAULT_POOL_SIZE: u32 = 10;
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+// command line flags, each falls back to its env var and then to the config file
+#[derive(StructOpt, Default)]
+#[structopt(name = "rust_crud")]
+pub struct Args {
+    /// TOML file with any of the settings below
+    #[structopt(long, env = "CONFIG_FILE", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+    /// address to listen on, e.g. 127.0.0.1:8000
+    #[structopt(long, env = "BIND")]
+    pub bind: Option<String>,
+    /// number of worker threads, defaults to the number of cpus
+    #[structopt(long, env = "WORKERS")]
+    pub workers: Option<usize>,
+    #[structopt(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+    #[structopt(long, env = "POOL_SIZE")]
+    pub pool_size: Option<u32>,
+    /// env_logger filter, e.g. info or rust_crud=debug
+    #[structopt(long, env = "LOG_LEVEL")]
+    pub log_level: Option<String>,
+    /// PEM certificate chain, serves https together with --tls-key
+    #[structopt(long, env = "TLS_CERT", parse(from_os_str))]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key (PKCS#8 or RSA)
+    #[structopt(long, env = "TLS_KEY", parse(from_os_str))]
+    pub tls_key: Option<PathBuf>,
+    /// key signing the login tokens, a random one invalidates tokens on restart
+    #[structopt(long, env = "JWT_SECRET", hide_env_values = true)]
+    pub jwt_secret: Option<String>,
+    /// creates the "admin" user on startup when there is no admin yet
+    #[structopt(long, env = "ADMIN_PASSWORD", hide_env_values = true)]
+    pub admin_password: Option<String>,
+    /// days deleted employees are kept before they are purged, forever when not set
+    #[structopt(long, env = "RETENTION_DAYS")]
+    pub retention_days: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    pub bind: Option<String>,
+    pub workers: Option<usize>,
+    pub database_url: Option<String>,
+    pub pool_size: Option<u32>,
+    pub log_level: Option<String>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub jwt_secret: Option<String>,
+    pub admin_password: Option<String>,
+    pub retention_days: Option<u32>,
+}
+
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+pub struct Config {
+    pub bind: String,
+    pub workers: Option<usize>,
+    pub database_url: String,
+    pub pool_size: u32,
+    pub log_level: String,
+    pub tls: Option<TlsConfig>,
+    pub jwt_secret: Option<String>,
+    pub admin_password: Option<String>,
+    pub retention_days: Option<u32>,
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let args = Args::from_args();
+        let file = match &args.config {
+            Some(path) => Some(FileConfig::read(path)?),
+            None => None,
+        };
+        Config::merge(args, file.unwrap_or_default())
+    }
+
+    pub fn merge(args: Args, file: FileConfig) -> Result<Config, String> {
+        let database_url = args
+            .database_url
+            .or(file.database_url)
+            .ok_or("database url is not set, use --database-url or DATABASE_URL")?;
+        let pool_size = args.pool_size.or(file.pool_size).unwrap_or(DEFAULT_POOL_SIZE);
+        if pool_size == 0 {
+            return Err("pool size must be at least 1".to_string());
+        }
+        let workers = args.workers.or(file.workers);
+        if workers == Some(0) {
+            return Err("workers must be at least 1".to_string());
+        }
+        let retention_days = args.retention_days.or(file.retention_days);
+        if retention_days == Some(0) {
+            return Err("retention days must be at least 1".to_string());
+        }
+        let tls = match (args.tls_cert.or(file.tls_cert), args.tls_key.or(file.tls_key)) {
+            (Some(cert), Some(key)) => Some(TlsConfig { cert, key }),
+            (None, None) => None,
+            _ => return Err("tls needs both a certificate and a key".to_string()),
+        };
+        Ok(Config {
+            bind: args.bind.or(file.bind).unwrap_or_else(|| DEFAULT_BIND.to_string()),
+            workers,
+            database_url,
+            pool_size,
+            log_level: args
+                .log_level
+                .or(file.log_level)
+                .unwrap_or_else(|| DEFAULT_LOG_LEVEL.to_string()),
+            tls,
+            jwt_secret: args.jwt_secret.or(file.jwt_secret),
+            admin_password: args.admin_password.or(file.admin_password),
+            retention_days,
+        })
+    }
+}
+
+impl FileConfig {
+    pub fn read(path: &PathBuf) -> Result<FileConfig, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        toml::from_str(&content).map_err(|e| format!("invalid config {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_override_file() {
+        let args = Args {
+            bind: Some("0.0.0.0:9000".to_string()),
+            pool_size: Some(4),
+            ..Args::default()
+        };
+        let file: FileConfig = toml::from_str(
+            r#"
+            bind = "127.0.0.1:1234"
+            database_url = "app.db"
+            pool_size = 2
+            workers = 3
+            retention_days = 30
+            "#,
+        )
+        .unwrap();
+        let config = Config::merge(args, file).unwrap();
+        assert_eq!(config.bind, "0.0.0.0:9000");
+        assert_eq!(config.database_url, "app.db");
+        assert_eq!(config.pool_size, 4);
+        assert_eq!(config.workers, Some(3));
+        assert_eq!(config.retention_days, Some(30));
+        assert_eq!(config.log_level, DEFAULT_LOG_LEVEL);
+        assert!(config.tls.is_none());
+    }
+
+    #[test]
+    fn test_defaults() {
+        let args = Args {
+            database_url: Some(":memory:".to_string()),
+            ..Args::default()
+        };
+        let config = Config::merge(args, FileConfig::default()).unwrap();
+        assert_eq!(config.bind, DEFAULT_BIND);
+        assert_eq!(config.pool_size, DEFAULT_POOL_SIZE);
+        assert_eq!(config.workers, None);
+        assert_eq!(config.retention_days, None);
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        assert!(Config::merge(Args::default(), FileConfig::default()).is_err());
+
+        let args = Args {
+            database_url: Some("app.db".to_string()),
+            tls_cert: Some(PathBuf::from("cert.pem")),
+            ..Args::default()
+        };
+        assert!(Config::merge(args, FileConfig::default()).is_err());
+
+        let args = Args {
+            database_url: Some("app.db".to_string()),
+            pool_size: Some(0),
+            ..Args::default()
+        };
+        assert!(Config::merge(args, FileConfig::default()).is_err());
+
+        let args = Args {
+            database_url: Some("app.db".to_string()),
+            retention_days: Some(0),
+            ..Args::default()
+        };
+        assert!(Config::merge(args, FileConfig::default()).is_err());
+
+        assert!(toml::from_str::<FileConfig>("port = 80").is_err());
+    }
+}
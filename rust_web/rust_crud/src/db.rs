@@ -1,33 +1,323 @@
 use crate::error_handler::CustomError;
 
-use diesel::r2d2::ConnectionManager;
-use diesel::sqlite;
-use diesel::sqlite::SqliteConnection;
-use lazy_static::lazy_static;
-use r2d2;
-use std::env;
-
-type Pool = r2d2::Pool<ConnectionManager<SqliteConnection>>;
-pub type DbConnection = r2d2::PooledConnection<ConnectionManager<SqliteConnection>>;
-
-embed_migrations!();
-
-// SqliteCOnnection::establish("test.db");
-lazy_static! {
-    static ref POOL: Pool = {
-        let db_url = env::var("DATABASE_URL").expect("database url is not set");
-        let manager = ConnectionManager::<SqliteConnection>::new(db_url);
-        Pool::new(manager).expect("failed to create db pool")
+use actix_web::web;
+
+use diesel::connection::SimpleConnection;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Error as PoolError};
+use diesel::sql_types::Text;
+
+// the backend is picked at build time, see the features in Cargo.toml
+#[cfg(feature = "postgres")]
+pub type Connection = diesel::pg::PgConnection;
+#[cfg(feature = "postgres")]
+pub type Backend = diesel::pg::Pg;
+#[cfg(not(feature = "postgres"))]
+pub type Connection = diesel::sqlite::SqliteConnection;
+#[cfg(not(feature = "postgres"))]
+pub type Backend = diesel::sqlite::Sqlite;
+
+#[cfg(not(any(feature = "sqlite", feature = "postgres")))]
+compile_error!("enable the sqlite or the postgres feature");
+
+pub type Pool = r2d2::Pool<ConnectionManager<Connection>>;
+pub type DbConnection = r2d2::PooledConnection<ConnectionManager<Connection>>;
+
+#[cfg(feature = "postgres")]
+embed_migrations!("migrations/postgres");
+#[cfg(not(feature = "postgres"))]
+embed_migrations!("migrations/sqlite");
+
+// case-insensitive comparisons that behave the same on both backends
+sql_function!(fn lower(x: Text) -> Text);
+
+#[cfg(not(feature = "postgres"))]
+no_arg_sql_function!(last_insert_rowid, diesel::sql_types::Integer);
+
+// inserts into a table and loads the new row, diesel 1.x has no RETURNING on sqlite
+#[cfg(feature = "postgres")]
+macro_rules! insert_returning {
+    ($conn:expr, $table:expr, $values:expr) => {
+        diesel::insert_into($table).values($values).get_result($conn)
     };
 }
+#[cfg(not(feature = "postgres"))]
+macro_rules! insert_returning {
+    ($conn:expr, $table:expr, $values:expr) => {
+        diesel::insert_into($table)
+            .values($values)
+            .execute($conn)
+            .and_then(|_| diesel::select($crate::db::last_insert_rowid).first::<i32>($conn))
+            .and_then(|id| $table.find(id).first($conn))
+    };
+}
+pub(crate) use insert_returning;
+
+// sqlite leaves foreign keys unchecked unless asked, per connection
+#[derive(Debug)]
+struct ForeignKeys;
+
+impl CustomizeConnection<Connection, PoolError> for ForeignKeys {
+    fn on_acquire(&self, conn: &mut Connection) -> Result<(), PoolError> {
+        if cfg!(feature = "postgres") {
+            return Ok(());
+        }
+        conn.batch_execute("PRAGMA foreign_keys = ON")
+            .map_err(PoolError::QueryError)
+    }
+}
+
+fn is_postgres_url(db_url: &str) -> bool {
+    db_url.starts_with("postgres://") || db_url.starts_with("postgresql://")
+}
+
+// the url scheme has to match the backend this binary was built for
+fn check_url(db_url: &str) -> Result<(), CustomError> {
+    match (cfg!(feature = "postgres"), is_postgres_url(db_url)) {
+        (true, false) => Err(CustomError::new(
+            500,
+            "This build uses postgres, the database url must start with postgres://".to_string(),
+        )),
+        (false, true) => Err(CustomError::new(
+            500,
+            "This build uses sqlite, rebuild with --features postgres for a postgres:// url".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
 
-pub fn init() {
-    lazy_static::initialize(&POOL);
-    let conn = connection().expect("failed to get db connection");
-    embedded_migrations::run(&conn).unwrap();
+pub fn pool(db_url: &str, max_size: u32) -> Result<Pool, CustomError> {
+    check_url(db_url)?;
+    let manager = ConnectionManager::<Connection>::new(db_url);
+    // every connection to :memory: opens its own empty database
+    let max_size = if db_url == ":memory:" { 1 } else { max_size };
+    Pool::builder()
+        .max_size(max_size)
+        .connection_customizer(Box::new(ForeignKeys))
+        .build(manager)
+        .map_err(|e| CustomError::new(500, format!("Failed creating db pool: {}", e)))
 }
 
-pub fn connection() -> Result<DbConnection, CustomError> {
-    POOL.get()
+pub fn init(pool: &Pool) -> Result<(), CustomError> {
+    let conn = connection(pool)?;
+    embedded_migrations::run(&conn)
+        .map_err(|e| CustomError::new(500, format!("Failed running migrations: {}", e)))
+}
+
+pub fn connection(pool: &Pool) -> Result<DbConnection, CustomError> {
+    pool.get()
         .map_err(|e| CustomError::new(500, format!("Failed getting db connection: {}", e)))
 }
+
+// diesel only blocks, so queries run on the actix blocking thread pool and the worker
+// keeps serving other requests meanwhile
+pub async fn run<F, T>(pool: &Pool, f: F) -> Result<T, CustomError>
+where
+    F: FnOnce(&Pool) -> Result<T, CustomError> + Send + 'static,
+    T: Send + 'static,
+{
+    let pool = pool.clone();
+    web::block(move || f(&pool)).await.map_err(CustomError::from)
+}
+
+// fresh in-memory database with migrations applied
+#[cfg(all(test, not(feature = "postgres")))]
+pub fn test_pool() -> Pool {
+    let pool = pool(":memory:", 1).expect("failed to create test db pool");
+    init(&pool).expect("failed to migrate test db");
+    pool
+}
+
+// fresh schema in the TEST_DATABASE_URL database with migrations applied
+#[cfg(all(test, feature = "postgres"))]
+pub fn test_pool() -> Pool {
+    test_schema_pool(1)
+}
+
+#[cfg(all(test, feature = "postgres"))]
+#[derive(Debug)]
+struct SearchPath(String);
+
+#[cfg(all(test, feature = "postgres"))]
+impl CustomizeConnection<Connection, PoolError> for SearchPath {
+    fn on_acquire(&self, conn: &mut Connection) -> Result<(), PoolError> {
+        conn.batch_execute(&format!("SET search_path TO {}", self.0))
+            .map_err(PoolError::QueryError)
+    }
+}
+
+// every test gets its own schema, so tests run in parallel and commit like the app does,
+// schemas of earlier runs are dropped when the next run starts
+#[cfg(all(test, feature = "postgres"))]
+pub fn test_schema_pool(max_size: u32) -> Pool {
+    use diesel::Connection as _;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Once;
+
+    static NEXT_SCHEMA: AtomicUsize = AtomicUsize::new(0);
+    static DROP_STALE: Once = Once::new();
+
+    let url = std::env::var("TEST_DATABASE_URL")
+        .unwrap_or_else(|_| "postgres://localhost/rust_crud_test".to_string());
+    let conn = Connection::establish(&url).expect("failed to connect to TEST_DATABASE_URL");
+    let prefix = format!("test_{}_", std::process::id());
+    DROP_STALE.call_once(|| {
+        conn.batch_execute(&format!(
+            "DO $$ DECLARE s text; BEGIN \
+                 FOR s IN SELECT nspname FROM pg_namespace \
+                          WHERE nspname LIKE 'test\\_%' AND nspname NOT LIKE '{}%' LOOP \
+                     EXECUTE format('DROP SCHEMA %I CASCADE', s); \
+                 END LOOP; \
+             END $$",
+            prefix
+        ))
+        .expect("failed to drop old test schemas");
+    });
+    let schema = format!("{}{}", prefix, NEXT_SCHEMA.fetch_add(1, Ordering::SeqCst));
+    conn.batch_execute(&format!("CREATE SCHEMA {}", schema))
+        .expect("failed to create test schema");
+    let pool = Pool::builder()
+        .max_size(max_size)
+        .connection_customizer(Box::new(SearchPath(schema)))
+        .build(ConnectionManager::new(url))
+        .expect("failed to create test db pool");
+    init(&pool).expect("failed to migrate test db");
+    pool
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::testing::{bearer, test_auth};
+    use crate::auth::{Authentication, Claims, Role};
+    use crate::departments;
+    use actix_web::{test, App, HttpResponse};
+    use futures::channel::oneshot;
+    use futures::future::{join, select, Either};
+    use std::sync::{mpsc, Mutex};
+    use std::time::Duration;
+
+    // stands in for a slow query, holds its thread until the test lets it go
+    struct Gate {
+        entered: Mutex<Option<oneshot::Sender<()>>>,
+        release: Mutex<mpsc::Receiver<()>>,
+    }
+
+    impl Gate {
+        fn hold(&self) {
+            if let Some(entered) = self.entered.lock().unwrap().take() {
+                let _ = entered.send(());
+            }
+            let _ = self.release.lock().unwrap().recv();
+        }
+    }
+
+    async fn slow(
+        pool: web::Data<Pool>,
+        gate: web::Data<Gate>,
+        _user: Claims,
+    ) -> Result<HttpResponse, CustomError> {
+        run(&pool, move |pool| {
+            let _conn = connection(pool)?;
+            gate.hold();
+            Ok(())
+        })
+        .await?;
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    // the old way, diesel called straight from the handler
+    async fn slow_inline(
+        pool: web::Data<Pool>,
+        gate: web::Data<Gate>,
+        _user: Claims,
+    ) -> Result<HttpResponse, CustomError> {
+        let _conn = connection(&pool)?;
+        gate.hold();
+        Ok(HttpResponse::Ok().finish())
+    }
+
+    // a file database, :memory: would share a single connection between both requests
+    #[cfg(not(feature = "postgres"))]
+    fn file_pool(name: &str) -> Pool {
+        let path = std::env::temp_dir().join(format!("rust_crud_{}_{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let pool = pool(path.to_str().unwrap(), 4).expect("failed to create db pool");
+        init(&pool).expect("failed to migrate db");
+        pool
+    }
+
+    #[cfg(feature = "postgres")]
+    fn file_pool(_name: &str) -> Pool {
+        test_schema_pool(4)
+    }
+
+    // holds the slow route on a single worker, then waits up to `patience` for GET /departments,
+    // returns whether it answered before the slow route was let go
+    async fn race(slow_path: &'static str, pool: Pool, patience: Duration) -> bool {
+        let (entered_tx, entered_rx) = oneshot::channel();
+        let (release_tx, release_rx) = mpsc::channel();
+        let gate = web::Data::new(Gate {
+            entered: Mutex::new(Some(entered_tx)),
+            release: Mutex::new(release_rx),
+        });
+        let srv = test::start(move || {
+            App::new()
+                .wrap(Authentication)
+                .data(pool.clone())
+                .data(test_auth())
+                .app_data(gate.clone())
+                .configure(departments::init_routes)
+                .route("/slow", web::get().to(slow))
+                .route("/slow_inline", web::get().to(slow_inline))
+        });
+        let slow_req = async {
+            let resp = srv
+                .get(slow_path)
+                .header("Authorization", bearer(Role::Viewer))
+                .timeout(Duration::from_secs(60))
+                .send()
+                .await
+                .unwrap();
+            assert!(resp.status().is_success());
+        };
+        let fast_req = async {
+            entered_rx.await.unwrap();
+            let fast = srv
+                .get("/departments")
+                .header("Authorization", bearer(Role::Viewer))
+                .timeout(Duration::from_secs(60))
+                .send();
+            let (resp, answered) = match select(fast, actix_rt::time::delay_for(patience)).await {
+                Either::Left((resp, _)) => (resp, true),
+                Either::Right((_, fast)) => {
+                    release_tx.send(()).unwrap();
+                    (fast.await, false)
+                }
+            };
+            let _ = release_tx.send(());
+            assert!(resp.unwrap().status().is_success());
+            answered
+        };
+        join(slow_req, fast_req).await.1
+    }
+
+    #[test]
+    fn test_url_must_match_backend() {
+        let postgres_url = "postgres://localhost/rust_crud";
+        if cfg!(feature = "postgres") {
+            assert!(check_url(postgres_url).is_ok());
+            assert!(check_url("app.db").is_err());
+        } else {
+            assert!(check_url("app.db").is_ok());
+            assert!(check_url(postgres_url).is_err());
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_slow_query_does_not_stall_other_requests() {
+        assert!(race("/slow", file_pool("load"), Duration::from_secs(60)).await);
+
+        // blocking the worker thread holds every other request until the query is done
+        assert!(!race("/slow_inline", file_pool("load_inline"), Duration::from_millis(500)).await);
+    }
+}
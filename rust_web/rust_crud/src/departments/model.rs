@@ -0,0 +1,124 @@
+use crate::db::{self, insert_returning, Pool};
+use crate::employees::Employees;
+use crate::error_handler::CustomError;
+use crate::schema::{departments, employees};
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Deserialize, Serialize, AsChangeset, Insertable, Validate, ToSchema)]
+#[table_name = "departments"]
+pub struct Department {
+    // unique ignoring case, so "Eng" and "eng" can't both exist
+    #[validate(length(min = 1, max = 100, message = "must be 1 to 100 characters"))]
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Queryable, Identifiable, Clone, Debug, PartialEq, ToSchema)]
+#[table_name = "departments"]
+pub struct Departments {
+    pub id: i32,
+    pub name: String,
+}
+
+impl Departments {
+    pub fn find_all(pool: &Pool) -> Result<Vec<Self>, CustomError> {
+        let conn = db::connection(pool)?;
+        let departments = departments::table
+            .order(departments::name.asc())
+            .load::<Departments>(&conn)?;
+        Ok(departments)
+    }
+
+    pub fn find(pool: &Pool, id: i32) -> Result<Self, CustomError> {
+        let conn = db::connection(pool)?;
+        let department = departments::table.filter(departments::id.eq(id)).first(&conn)?;
+        Ok(department)
+    }
+
+    pub fn find_by_ids(conn: &db::Connection, ids: &[i32]) -> Result<Vec<Self>, CustomError> {
+        let departments = departments::table
+            .filter(departments::id.eq_any(ids))
+            .load::<Departments>(conn)?;
+        Ok(departments)
+    }
+
+    pub fn exists(conn: &db::Connection, id: i32) -> Result<bool, CustomError> {
+        let found = diesel::select(exists(departments::table.filter(departments::id.eq(id))))
+            .get_result(conn)?;
+        Ok(found)
+    }
+
+    pub fn employees(pool: &Pool, id: i32) -> Result<Vec<Employees>, CustomError> {
+        let conn = db::connection(pool)?;
+        let department: Departments = departments::table.filter(departments::id.eq(id)).first(&conn)?;
+        let employees = Employees::belonging_to(&department)
+            .filter(employees::deleted_at.is_null())
+            .order(employees::id.asc())
+            .load::<Employees>(&conn)?;
+        Ok(employees)
+    }
+
+    pub fn create(pool: &Pool, department: Department) -> Result<Self, CustomError> {
+        let department = Department {
+            name: department.name.trim().to_string(),
+        };
+        department.validate()?;
+        let conn = db::connection(pool)?;
+        let department = insert_returning!(&conn, departments::table, department)?;
+        Ok(department)
+    }
+
+    pub fn update(pool: &Pool, id: i32, department: Department) -> Result<Self, CustomError> {
+        let department = Department {
+            name: department.name.trim().to_string(),
+        };
+        department.validate()?;
+        let conn = db::connection(pool)?;
+        let updated = diesel::update(departments::table.filter(departments::id.eq(id)))
+            .set(department)
+            .execute(&conn)?;
+        if updated == 0 {
+            return Err(DieselError::NotFound.into());
+        }
+        let department = departments::table.filter(departments::id.eq(id)).first(&conn)?;
+        Ok(department)
+    }
+
+    // fails with 409 while employees still belong to the department, deleted ones included
+    pub fn delete(pool: &Pool, id: i32) -> Result<usize, CustomError> {
+        let conn = db::connection(pool)?;
+        conn.transaction(|| {
+            let members = employees::table
+                .filter(employees::department_id.eq(id))
+                .select((employees::id, employees::deleted_at))
+                .order(employees::id.asc())
+                .load::<(i32, Option<String>)>(&conn)?;
+            let active = members.iter().filter(|(_, deleted_at)| deleted_at.is_none()).count();
+            if active > 0 {
+                return Err(CustomError::new(
+                    409,
+                    format!("{} employees still belong to the department", active),
+                ));
+            }
+            if !members.is_empty() {
+                return Err(CustomError::new(
+                    409,
+                    format!(
+                        "{} deleted employees still belong to the department, ask an admin to restore or \
+                         purge them",
+                        members.len()
+                    ),
+                ));
+            }
+            let deleted = diesel::delete(departments::table.filter(departments::id.eq(id))).execute(&conn)?;
+            if deleted == 0 {
+                return Err(DieselError::NotFound.into());
+            }
+            Ok(deleted)
+        })
+    }
+}
@@ -0,0 +1,302 @@
+use crate::auth::{Claims, Role};
+use crate::db::{self, Pool};
+use crate::departments::{Department, Departments};
+use crate::employees::Employees;
+use crate::error_handler::{CustomError, ErrorResponse};
+use crate::openapi::Deleted;
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use utoipa::OpenApi;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(find_all, find, employees, create, update, delete),
+    components(schemas(Department, Departments))
+)]
+pub struct DepartmentsApi;
+
+/// All departments by name
+#[utoipa::path(get, path = "/departments", tag = "departments", responses((status = 200, body = Vec<Departments>)))]
+#[get("/departments")]
+async fn find_all(pool: web::Data<Pool>, _user: Claims) -> Result<HttpResponse, CustomError> {
+    let departments = db::run(&pool, Departments::find_all).await?;
+    Ok(HttpResponse::Ok().json(departments))
+}
+
+#[utoipa::path(
+    get, path = "/departments/{id}", tag = "departments",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Departments),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+#[get("/departments/{id}")]
+async fn find(
+    pool: web::Data<Pool>,
+    _user: Claims,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, CustomError> {
+    let department = db::run(&pool, move |pool| Departments::find(pool, id.into_inner())).await?;
+    Ok(HttpResponse::Ok().json(department))
+}
+
+/// Employees of a department, salaries are left out for viewers
+#[utoipa::path(
+    get, path = "/departments/{id}/employees", tag = "departments",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Vec<Employees>),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+#[get("/departments/{id}/employees")]
+async fn employees(
+    pool: web::Data<Pool>,
+    user: Claims,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, CustomError> {
+    let employees = db::run(&pool, move |pool| {
+        Departments::employees(pool, id.into_inner())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(user.redact(&employees)?))
+}
+
+#[utoipa::path(
+    post, path = "/departments", tag = "departments",
+    request_body = Department,
+    responses(
+        (status = 200, body = Departments),
+        (status = 403, body = ErrorResponse),
+        (status = 409, description = "The name is taken, ignoring case", body = ErrorResponse),
+        (status = 422, body = ErrorResponse),
+    )
+)]
+#[post("/departments")]
+async fn create(
+    pool: web::Data<Pool>,
+    user: Claims,
+    department: web::Json<Department>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let department = db::run(&pool, move |pool| {
+        Departments::create(pool, department.into_inner())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(department))
+}
+
+#[utoipa::path(
+    put, path = "/departments/{id}", tag = "departments",
+    params(("id" = i32, Path)),
+    request_body = Department,
+    responses(
+        (status = 200, body = Departments),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "The name is taken, ignoring case", body = ErrorResponse),
+        (status = 422, body = ErrorResponse),
+    )
+)]
+#[put("/departments/{id}")]
+async fn update(
+    pool: web::Data<Pool>,
+    user: Claims,
+    id: web::Path<i32>,
+    department: web::Json<Department>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let department = db::run(&pool, move |pool| {
+        Departments::update(pool, id.into_inner(), department.into_inner())
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(department))
+}
+
+#[utoipa::path(
+    delete, path = "/departments/{id}", tag = "departments",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Deleted),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Employees still belong to the department, deleted ones included", body = ErrorResponse),
+    )
+)]
+#[delete("/departments/{id}")]
+async fn delete(
+    pool: web::Data<Pool>,
+    user: Claims,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let deleted = db::run(&pool, move |pool| Departments::delete(pool, id.into_inner())).await?;
+    Ok(HttpResponse::Ok().json(Deleted { deleted }))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(find_all);
+    config.service(find);
+    config.service(employees);
+    config.service(create);
+    config.service(update);
+    config.service(delete);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::testing::{bearer, test_app};
+    use crate::db;
+    use crate::employees::Employee;
+    use crate::etag::IfMatch;
+    use crate::schema::employees;
+    use actix_web::{http::StatusCode, test};
+    use diesel::prelude::*;
+    use serde_json::{json, Value};
+
+    #[actix_rt::test]
+    async fn test_department_crud() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/departments")
+            .set_json(&json!({ "name": " Sales " }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let created: Value = test::read_body_json(resp).await;
+        assert_eq!(created["name"], json!("Sales"));
+        let id = created["id"].as_i64().unwrap();
+
+        let req = test::TestRequest::put()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/departments/{}", id))
+            .set_json(&json!({ "name": "Marketing" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/departments/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let found: Value = test::read_body_json(resp).await;
+        assert_eq!(found["name"], json!("Marketing"));
+
+        // the initial employees migration seeds the "Dept" department
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/departments")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let all: Value = test::read_body_json(resp).await;
+        assert_eq!(all, json!([{ "id": 1, "name": "Dept" }, { "id": id, "name": "Marketing" }]));
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/departments/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/departments/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_duplicate_department_is_409() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/departments")
+            .set_json(&json!({ "name": "dept" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/departments")
+            .set_json(&json!({ "name": "  " }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[actix_rt::test]
+    async fn test_department_employees() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/departments/1/employees")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let members: Value = test::read_body_json(resp).await;
+        assert_eq!(members[0]["last_name"], json!("Admin"));
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/departments/999/employees")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        // still referenced by the admin
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/departments/1")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+    }
+
+    #[actix_rt::test]
+    async fn test_deleted_employees_keep_their_department() {
+        let pool = db::test_pool();
+        let sales = Departments::create(&pool, Department { name: "Sales".to_string() }).unwrap();
+        let employee = Employee {
+            first_name: "Test".to_string(),
+            last_name: "Person".to_string(),
+            department_id: sales.id,
+            salary: 1000,
+            age: 30,
+        };
+        let employee = Employees::create(&pool, "test", employee).unwrap();
+        Employees::delete(&pool, "test", employee.id, &IfMatch::Any).unwrap();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/departments/{}", sales.id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let error: Value = test::read_body_json(resp).await;
+        let message = error["message"].as_str().unwrap();
+        assert!(message.starts_with("1 deleted employees"), "{}", message);
+        assert!(message.contains("ask an admin"), "{}", message);
+
+        // as the purge leaves it
+        diesel::delete(employees::table.find(employee.id))
+            .execute(&db::connection(&pool).unwrap())
+            .unwrap();
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/departments/{}", sales.id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::db::{self, Pool};
+use crate::employees::Employees;
+use crate::error_handler::CustomError;
+use crate::schema::{employee_audit, employees};
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel::result::Error as DieselError;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy)]
+pub enum Operation {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    Purge,
+}
+
+impl Operation {
+    fn as_str(self) -> &'static str {
+        match self {
+            Operation::Create => "create",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+            Operation::Restore => "restore",
+            Operation::Purge => "purge",
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "employee_audit"]
+struct NewAuditEntry<'a> {
+    employee_id: i32,
+    actor: &'a str,
+    operation: &'a str,
+    changes: String,
+}
+
+#[derive(Queryable)]
+struct AuditRow {
+    id: i32,
+    employee_id: i32,
+    actor: String,
+    changed_at: String,
+    operation: String,
+    changes: String,
+}
+
+// one entry of GET /employees/{id}/history
+#[derive(Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub employee_id: i32,
+    pub actor: String,
+    pub changed_at: String,
+    pub operation: String,
+    // {field: {"before": .., "after": ..}} for every field that changed
+    pub changes: Value,
+}
+
+impl From<AuditRow> for AuditEntry {
+    fn from(row: AuditRow) -> AuditEntry {
+        AuditEntry {
+            id: row.id,
+            employee_id: row.employee_id,
+            actor: row.actor,
+            changed_at: row.changed_at,
+            operation: row.operation,
+            changes: serde_json::from_str(&row.changes).unwrap_or(Value::Null),
+        }
+    }
+}
+
+fn fields(employee: Option<&Employees>) -> Result<Map<String, Value>, CustomError> {
+    match employee.map(serde_json::to_value).transpose() {
+        Ok(Some(Value::Object(fields))) => Ok(fields),
+        Ok(_) => Ok(Map::new()),
+        Err(e) => Err(CustomError::new(500, format!("Failed serializing employee: {}", e))),
+    }
+}
+
+fn diff(before: Option<&Employees>, after: Option<&Employees>) -> Result<Map<String, Value>, CustomError> {
+    let before = fields(before)?;
+    let after = fields(after)?;
+    let mut changes = Map::new();
+    // the version moves with every update, it is not a change of its own
+    for key in before.keys().chain(after.keys()).filter(|key| *key != "version") {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new && !changes.contains_key(key) {
+            changes.insert(key.clone(), json!({ "before": old, "after": new }));
+        }
+    }
+    Ok(changes)
+}
+
+// called inside the transaction of the change itself, nothing is written for a no-op update
+pub fn record(
+    conn: &db::Connection,
+    actor: &str,
+    operation: Operation,
+    before: Option<&Employees>,
+    after: Option<&Employees>,
+) -> Result<(), CustomError> {
+    let employee_id = match after.or(before) {
+        Some(employee) => employee.id,
+        None => return Ok(()),
+    };
+    let changes = diff(before, after)?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let entry = NewAuditEntry {
+        employee_id,
+        actor,
+        operation: operation.as_str(),
+        changes: Value::Object(changes).to_string(),
+    };
+    diesel::insert_into(employee_audit::table)
+        .values(entry)
+        .execute(conn)?;
+    Ok(())
+}
+
+// the entries oldest first, and whether the employee is still live, neither deleted nor purged
+pub fn history(pool: &Pool, employee_id: i32) -> Result<(Vec<AuditEntry>, bool), CustomError> {
+    let conn = db::connection(pool)?;
+    let rows = employee_audit::table
+        .filter(employee_audit::employee_id.eq(employee_id))
+        .order(employee_audit::id.asc())
+        .load::<AuditRow>(&conn)?;
+    let live: bool = diesel::select(exists(
+        employees::table
+            .filter(employees::id.eq(employee_id))
+            .filter(employees::deleted_at.is_null()),
+    ))
+    .get_result(&conn)?;
+    if rows.is_empty() && !live {
+        let found: bool = diesel::select(exists(employees::table.filter(employees::id.eq(employee_id))))
+            .get_result(&conn)?;
+        if !found {
+            return Err(DieselError::NotFound.into());
+        }
+    }
+    Ok((rows.into_iter().map(AuditEntry::from).collect(), live))
+}
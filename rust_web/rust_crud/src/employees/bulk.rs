@@ -0,0 +1,225 @@
+use crate::auth::Claims;
+use crate::db::{self, insert_returning, Pool};
+use crate::employees::audit::{self, Operation};
+use crate::employees::{unknown_department, Employee, Employees};
+use crate::error_handler::CustomError;
+use crate::schema::{departments, employees};
+use actix_web::web::Bytes;
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use utoipa::ToSchema;
+use validator::Validate;
+
+pub const EXPORT_BATCH_SIZE: i64 = 500;
+
+// columns of the csv export, in order
+const EXPORT_COLUMNS: [&str; 6] = ["id", "first_name", "last_name", "department_id", "salary", "age"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Csv,
+    Ndjson,
+}
+
+impl Format {
+    pub fn parse(format: &str) -> Result<Format, CustomError> {
+        match format {
+            "csv" => Ok(Format::Csv),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(CustomError::new(400, format!("Unknown format '{}', use csv or ndjson", format))),
+        }
+    }
+
+    // the mime type of a Content-Type header, without parameters
+    pub fn from_mime(mime: &str) -> Option<Format> {
+        match mime {
+            "text/csv" => Some(Format::Csv),
+            "application/x-ndjson" | "application/jsonl" | "application/x-jsonlines" => Some(Format::Ndjson),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Ndjson => "application/x-ndjson",
+        }
+    }
+}
+
+// a row that was not imported, lines count from 1 and include the csv header
+#[derive(Serialize, ToSchema)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, Vec<String>>,
+}
+
+impl RowError {
+    fn new(line: u64, error: CustomError) -> RowError {
+        RowError {
+            line,
+            message: error.error_message,
+            errors: error.field_errors,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportSummary {
+    pub dry_run: bool,
+    pub rows: usize,
+    // nothing is imported on a dry run or when any row is invalid
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+}
+
+pub type ImportRow = (u64, Result<Employee, CustomError>);
+
+fn bad_row(error: impl std::fmt::Display) -> CustomError {
+    CustomError::new(422, error.to_string())
+}
+
+// rows of an import body with the line each starts on, columns are matched by the csv
+// header and extra ones like "id" are ignored, so an export can be imported again
+pub fn parse(format: Format, body: &[u8]) -> Vec<ImportRow> {
+    match format {
+        Format::Csv => parse_csv(body),
+        Format::Ndjson => parse_ndjson(body),
+    }
+}
+
+fn parse_csv(body: &[u8]) -> Vec<ImportRow> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(body);
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => return vec![(1, Err(bad_row(e)))],
+    };
+    reader
+        .records()
+        .map(|record| match record {
+            Ok(record) => {
+                let line = record.position().map_or(0, |position| position.line());
+                (line, record.deserialize(Some(&headers)).map_err(bad_row))
+            }
+            Err(e) => (e.position().map_or(0, |position| position.line()), Err(bad_row(e))),
+        })
+        .collect()
+}
+
+fn parse_ndjson(body: &[u8]) -> Vec<ImportRow> {
+    body.split(|byte| *byte == b'\n')
+        .enumerate()
+        .filter(|(_, line)| !line.iter().all(u8::is_ascii_whitespace))
+        .map(|(index, line)| (index as u64 + 1, serde_json::from_slice(line).map_err(bad_row)))
+        .collect()
+}
+
+impl Employees {
+    // validates every row first, then inserts all of them in one transaction
+    pub fn import(pool: &Pool, actor: &str, rows: Vec<ImportRow>, dry_run: bool) -> Result<ImportSummary, CustomError> {
+        let conn = db::connection(pool)?;
+        let departments: HashSet<i32> = departments::table
+            .select(departments::id)
+            .load::<i32>(&conn)?
+            .into_iter()
+            .collect();
+
+        let total = rows.len();
+        let mut valid = Vec::new();
+        let mut errors = Vec::new();
+        for (line, row) in rows {
+            let checked = row.and_then(|employee| {
+                employee.validate()?;
+                if !departments.contains(&employee.department_id) {
+                    return Err(unknown_department());
+                }
+                Ok(employee)
+            });
+            match checked {
+                Ok(employee) => valid.push(employee),
+                Err(e) => errors.push(RowError::new(line, e)),
+            }
+        }
+
+        let mut summary = ImportSummary {
+            dry_run,
+            rows: total,
+            imported: 0,
+            errors,
+        };
+        if dry_run || !summary.errors.is_empty() {
+            return Ok(summary);
+        }
+        conn.transaction::<_, CustomError, _>(|| {
+            for employee in valid {
+                let created: Employees = insert_returning!(&conn, employees::table, employee)?;
+                audit::record(&conn, actor, Operation::Create, None, Some(&created))?;
+            }
+            Ok(())
+        })?;
+        summary.imported = total;
+        Ok(summary)
+    }
+
+    // the export reads the table in batches after the last id seen instead of all at once
+    pub fn export_batch(pool: &Pool, after_id: i32) -> Result<Vec<Self>, CustomError> {
+        let conn = db::connection(pool)?;
+        let employees = employees::table
+            .filter(employees::id.gt(after_id))
+            .filter(employees::deleted_at.is_null())
+            .order(employees::id.asc())
+            .limit(EXPORT_BATCH_SIZE)
+            .load::<Employees>(&conn)?;
+        Ok(employees)
+    }
+}
+
+fn export_columns(user: &Claims) -> Vec<&'static str> {
+    EXPORT_COLUMNS.iter().copied().filter(|column| user.can_see(column)).collect()
+}
+
+fn write_failed(error: impl std::fmt::Display) -> CustomError {
+    CustomError::new(500, format!("Failed writing export: {}", error))
+}
+
+// the first chunk of an export, the csv header line
+pub fn export_header(format: Format, user: &Claims) -> Result<Option<Bytes>, CustomError> {
+    if format != Format::Csv {
+        return Ok(None);
+    }
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(export_columns(user)).map_err(write_failed)?;
+    let header = writer.into_inner().map_err(write_failed)?;
+    Ok(Some(Bytes::from(header)))
+}
+
+// one chunk of an export, without the fields hidden from the user
+pub fn export_rows(format: Format, user: &Claims, employees: &[Employees]) -> Result<Bytes, CustomError> {
+    let mut out = Vec::new();
+    match format {
+        Format::Csv => {
+            let columns = export_columns(user);
+            let mut writer = csv::Writer::from_writer(&mut out);
+            for employee in employees {
+                let row = user.redact(employee)?;
+                let record = columns.iter().map(|column| match &row[*column] {
+                    Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                });
+                writer.write_record(record).map_err(write_failed)?;
+            }
+            writer.flush().map_err(write_failed)?;
+        }
+        Format::Ndjson => {
+            for employee in employees {
+                serde_json::to_writer(&mut out, &user.redact(employee)?).map_err(write_failed)?;
+                out.push(b'\n');
+            }
+        }
+    }
+    Ok(Bytes::from(out))
+}
@@ -1,5 +1,13 @@
+mod audit;
+mod bulk;
 mod route;
 mod model;
+mod purge;
+mod search;
 
-pub use route::init_routes;
+pub use audit::*;
+pub use bulk::*;
+pub use route::{init_routes, EmployeesApi};
 pub use model::*;
+pub use purge::*;
+pub use search::*;
@@ -1,16 +1,28 @@
-use crate::db;
+use crate::db::{self, insert_returning, lower, Backend, Pool};
+use crate::departments::Departments;
+use crate::employees::audit::{self, Operation};
 use crate::error_handler::CustomError;
-use crate::schema::employees;
+use crate::etag::{precondition_failed, IfMatch};
+use crate::schema::{departments, employees};
+use diesel::dsl::sql;
 use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Text};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError, ValidationErrors};
 
-#[derive(Deserialize,Serialize, AsChangeset, Insertable)]
+#[derive(Deserialize,Serialize, AsChangeset, Insertable, Validate, ToSchema)]
 #[table_name = "employees"]
 pub struct Employee {
+    #[validate(length(min = 1, max = 100, message = "must be 1 to 100 characters"))]
     pub first_name: String,
+    #[validate(length(min = 1, max = 100, message = "must be 1 to 100 characters"))]
     pub last_name: String,
-    pub department: String,
+    pub department_id: i32,
+    #[validate(range(min = 0, message = "must not be negative"))]
     pub salary: i32,
+    #[validate(range(min = 16, max = 100, message = "must be between 16 and 100"))]
     pub age: i32,
 }
 
@@ -19,51 +31,431 @@ impl Employee {
         Employee {
             first_name: employee.first_name,
             last_name: employee.last_name,
-            department: employee.department,
+            department_id: employee.department_id,
             salary: employee.salary,
             age: employee.age
         }
     }
 }
 
+// body of PATCH /employees/{id}, fields left out are kept
+#[derive(Deserialize, AsChangeset, Default, Validate, ToSchema)]
+#[table_name = "employees"]
+pub struct EmployeePatch {
+    #[validate(length(min = 1, max = 100, message = "must be 1 to 100 characters"))]
+    pub first_name: Option<String>,
+    #[validate(length(min = 1, max = 100, message = "must be 1 to 100 characters"))]
+    pub last_name: Option<String>,
+    pub department_id: Option<i32>,
+    #[validate(range(min = 0, message = "must not be negative"))]
+    pub salary: Option<i32>,
+    #[validate(range(min = 16, max = 100, message = "must be between 16 and 100"))]
+    pub age: Option<i32>,
+}
+
+impl EmployeePatch {
+    fn is_empty(&self) -> bool {
+        self.first_name.is_none()
+            && self.last_name.is_none()
+            && self.department_id.is_none()
+            && self.salary.is_none()
+            && self.age.is_none()
+    }
+}
+
+// unknown departments are reported like the other field errors instead of a 409 from the foreign key
+fn check_department(conn: &db::Connection, department_id: i32) -> Result<(), CustomError> {
+    if Departments::exists(conn, department_id)? {
+        return Ok(());
+    }
+    Err(unknown_department())
+}
+
+pub(crate) fn unknown_department() -> CustomError {
+    let mut error = ValidationError::new("department_id");
+    error.message = Some("unknown department".into());
+    let mut errors = ValidationErrors::new();
+    errors.add("department_id", error);
+    errors.into()
+}
+
+// sql for the utc time `days_ago` days back, formatted like changed_at in the audit
+#[cfg(not(feature = "postgres"))]
+pub(crate) fn timestamp(days_ago: u32) -> String {
+    format!("strftime('%Y-%m-%dT%H:%M:%fZ', 'now', '-{} days')", days_ago)
+}
+
+#[cfg(feature = "postgres")]
+pub(crate) fn timestamp(days_ago: u32) -> String {
+    format!(
+        "to_char((now() - interval '{} days') AT TIME ZONE 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"')",
+        days_ago
+    )
+}
+
+// the row as long as nobody else changed it, a concurrent write in between matches nothing
+fn current(
+    before: &Employees,
+) -> diesel::dsl::Filter<diesel::dsl::Find<employees::table, i32>, diesel::dsl::Eq<employees::version, i32>> {
+    employees::table.find(before.id).filter(employees::version.eq(before.version))
+}
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 500;
+
+// query string of GET /employees
+#[derive(Deserialize, Default, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct EmployeeQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub cursor: Option<i32>, // id of the last employee seen, only with the default sort by id
+    pub department_id: Option<i32>,
+    pub department: Option<String>, // department name, ignoring case
+    pub min_salary: Option<i32>,
+    pub max_salary: Option<i32>,
+    pub min_age: Option<i32>,
+    pub max_age: Option<i32>,
+    pub name_prefix: Option<String>, // first or last name
+    pub sort: Option<String>, // field:asc|desc
+    pub embed: Option<String>, // "department" adds the department object to each item
+    #[serde(default)]
+    pub include_deleted: bool, // admins only
+}
+
+// employee in a list, with its department when embedded
+#[derive(Serialize, ToSchema)]
+pub struct EmployeeItem {
+    #[serde(flatten)]
+    pub employee: Employees,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub department: Option<Departments>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EmployeePage {
+    pub items: Vec<EmployeeItem>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    pub next_cursor: Option<i32>,
+}
 
-no_arg_sql_function!(last_insert_rowid, diesel::sql_types::Integer);
+#[derive(Clone, Copy, PartialEq)]
+enum SortField {
+    Id,
+    FirstName,
+    LastName,
+    Department,
+    Salary,
+    Age,
+}
+
+impl EmployeeQuery {
+    pub fn uses_salary(&self) -> bool {
+        self.min_salary.is_some()
+            || self.max_salary.is_some()
+            || self.sort.as_deref().is_some_and(|sort| sort.starts_with("salary"))
+    }
+
+    fn sort_order(&self) -> Result<(SortField, bool), CustomError> {
+        let sort = match &self.sort {
+            Some(sort) => sort,
+            None => return Ok((SortField::Id, true)),
+        };
+        let (field, direction) = match sort.split_once(':') {
+            Some((field, direction)) => (field, direction),
+            None => (sort.as_str(), "asc"),
+        };
+        let field = match field {
+            "id" => SortField::Id,
+            "first_name" => SortField::FirstName,
+            "last_name" => SortField::LastName,
+            "department_id" => SortField::Department,
+            "salary" => SortField::Salary,
+            "age" => SortField::Age,
+            _ => return Err(CustomError::new(400, format!("Cannot sort by '{}'", field))),
+        };
+        let ascending = match direction {
+            "asc" => true,
+            "desc" => false,
+            _ => return Err(CustomError::new(400, format!("Unknown sort direction '{}'", direction))),
+        };
+        Ok((field, ascending))
+    }
+
+    // rows matching the filters, shared by the page and the total count
+    pub(crate) fn filtered(&self) -> employees::BoxedQuery<'_, Backend> {
+        let mut query = employees::table.into_boxed();
+        if !self.include_deleted {
+            query = query.filter(employees::deleted_at.is_null());
+        }
+        if let Some(department_id) = self.department_id {
+            query = query.filter(employees::department_id.eq(department_id));
+        }
+        if let Some(department) = &self.department {
+            let ids = departments::table
+                .select(departments::id)
+                .filter(lower(departments::name).eq(lower(department.trim())));
+            query = query.filter(employees::department_id.eq_any(ids));
+        }
+        if let Some(min_salary) = self.min_salary {
+            query = query.filter(employees::salary.ge(min_salary));
+        }
+        if let Some(max_salary) = self.max_salary {
+            query = query.filter(employees::salary.le(max_salary));
+        }
+        if let Some(min_age) = self.min_age {
+            query = query.filter(employees::age.ge(min_age));
+        }
+        if let Some(max_age) = self.max_age {
+            query = query.filter(employees::age.le(max_age));
+        }
+        if let Some(prefix) = &self.name_prefix {
+            // lowercased on both sides, LIKE ignores case on sqlite but not on postgres
+            let prefix = prefix.to_lowercase();
+            let pattern = format!("{}%", prefix.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+            query = query.filter(
+                lower(employees::first_name)
+                    .like(pattern.clone())
+                    .escape('\\')
+                    .or(lower(employees::last_name).like(pattern).escape('\\')),
+            );
+        }
+        query
+    }
+}
 
-#[derive(Deserialize,Serialize,Queryable, Insertable)]
+#[derive(Deserialize,Serialize,Queryable, Insertable, Identifiable, Associations, ToSchema)]
+#[belongs_to(Departments, foreign_key = "department_id")]
 #[table_name = "employees"]
 pub struct Employees {
     pub id: i32,
     pub first_name: String,
     pub last_name: String,
-    pub department: String,
+    pub department_id: i32,
+    // left out for viewers
+    #[schema(required = false)]
     pub salary: i32,
     pub age: i32,
+    pub version: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<String>,
 }
 
 impl Employees {
-    pub fn find_all() -> Result<Vec<Self>, CustomError> {
-        let conn = db::connection()?;
-        let employees = employees::table.load::<Employees>(&conn)?;
-        Ok(employees)
+    pub fn find_page(pool: &Pool, params: &EmployeeQuery) -> Result<EmployeePage, CustomError> {
+        let (sort, ascending) = params.sort_order()?;
+        let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+            return Err(CustomError::new(400, format!("limit must be between 1 and {}", MAX_PAGE_SIZE)));
+        }
+        let offset = params.offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(CustomError::new(400, "offset must not be negative".to_string()));
+        }
+        if params.cursor.is_some() && (sort != SortField::Id || !ascending) {
+            return Err(CustomError::new(400, "cursor requires sorting by id:asc".to_string()));
+        }
+
+        let embed_department = match params.embed.as_deref() {
+            None => false,
+            Some("department") => true,
+            Some(other) => return Err(CustomError::new(400, format!("Cannot embed '{}'", other))),
+        };
+
+        let conn = db::connection(pool)?;
+        let total = params.filtered().count().get_result::<i64>(&conn)?;
+
+        let mut query = params.filtered();
+        if let Some(cursor) = params.cursor {
+            query = query.filter(employees::id.gt(cursor));
+        }
+        query = match (sort, ascending) {
+            (SortField::Id, true) => query.order(employees::id.asc()),
+            (SortField::Id, false) => query.order(employees::id.desc()),
+            (SortField::FirstName, true) => query.order(employees::first_name.asc()),
+            (SortField::FirstName, false) => query.order(employees::first_name.desc()),
+            (SortField::LastName, true) => query.order(employees::last_name.asc()),
+            (SortField::LastName, false) => query.order(employees::last_name.desc()),
+            (SortField::Department, true) => query.order(employees::department_id.asc()),
+            (SortField::Department, false) => query.order(employees::department_id.desc()),
+            (SortField::Salary, true) => query.order(employees::salary.asc()),
+            (SortField::Salary, false) => query.order(employees::salary.desc()),
+            (SortField::Age, true) => query.order(employees::age.asc()),
+            (SortField::Age, false) => query.order(employees::age.desc()),
+        };
+        // ties broken by id so pages don't overlap, one extra row tells whether there is a next page
+        let mut items = query
+            .then_order_by(employees::id.asc())
+            .limit(limit + 1)
+            .offset(offset)
+            .load::<Employees>(&conn)?;
+        let more = items.len() as i64 > limit;
+        items.truncate(limit as usize);
+
+        let next_cursor = match (sort, ascending, items.last()) {
+            (SortField::Id, true, Some(last)) if more => Some(last.id),
+            _ => None,
+        };
+        let mut departments = HashMap::new();
+        if embed_department {
+            let ids: Vec<i32> = items.iter().map(|employee| employee.department_id).collect();
+            for department in Departments::find_by_ids(&conn, &ids)? {
+                departments.insert(department.id, department);
+            }
+        }
+        let items = items
+            .into_iter()
+            .map(|employee| EmployeeItem {
+                department: departments.get(&employee.department_id).cloned(),
+                employee,
+            })
+            .collect();
+        Ok(EmployeePage {
+            items,
+            total,
+            limit,
+            offset,
+            next_cursor,
+        })
+    }
+
+    pub fn find(pool: &Pool, id: i32) -> Result<Self, CustomError> {
+        let conn = db::connection(pool)?;
+        let employee = employees::table
+            .filter(employees::id.eq(id))
+            .filter(employees::deleted_at.is_null())
+            .first(&conn)?;
+        Ok(employee)
     }
 
-    pub fn find(id: i32) -> Result<Self, CustomError> {
-        let conn = db::connection()?;
+    // deleted employees too, until they are purged
+    pub fn find_with_deleted(pool: &Pool, id: i32) -> Result<Self, CustomError> {
+        let conn = db::connection(pool)?;
         let employee = employees::table.filter(employees::id.eq(id)).first(&conn)?;
         Ok(employee)
     }
 
-    pub fn create(employee: Employee) -> Result<Self, CustomError>
+    pub fn create(pool: &Pool, actor: &str, employee: Employee) -> Result<Self, CustomError>
     {
-        let conn = db::connection()?;
+        employee.validate()?;
+        let conn = db::connection(pool)?;
+        check_department(&conn, employee.department_id)?;
         let employee = Employee::from(employee);
-        let emp = diesel::insert_into(employees::table)
-            .values(employee)
-            .execute(&conn)?;
-        //Ok(emp)
-        //let last = employees::table.find(1).last::<Employees>(&conn).expect("row just inserted!");
-        let last_id: i32 = diesel::select(last_insert_rowid).first(&conn).expect("row just inserted, must exist");
-        let last = employees::table.filter(employees::id.eq(last_id)).first(&conn)?;
-        Ok(last)
+        conn.transaction(|| {
+            let last = insert_returning!(&conn, employees::table, employee)?;
+            audit::record(&conn, actor, Operation::Create, None, Some(&last))?;
+            Ok(last)
+        })
+    }
+
+    pub fn update(
+        pool: &Pool,
+        actor: &str,
+        id: i32,
+        if_match: &IfMatch,
+        employee: Employee,
+    ) -> Result<Self, CustomError> {
+        employee.validate()?;
+        let conn = db::connection(pool)?;
+        check_department(&conn, employee.department_id)?;
+        conn.transaction(|| {
+            let before: Employees = employees::table
+                .filter(employees::id.eq(id))
+                .filter(employees::deleted_at.is_null())
+                .first(&conn)?;
+            if_match.check(before.version)?;
+            let updated = diesel::update(current(&before))
+                .set((employee, employees::version.eq(employees::version + 1)))
+                .execute(&conn)?;
+            if updated == 0 {
+                return Err(precondition_failed());
+            }
+            let after = employees::table.filter(employees::id.eq(id)).first(&conn)?;
+            audit::record(&conn, actor, Operation::Update, Some(&before), Some(&after))?;
+            Ok(after)
+        })
+    }
+
+    pub fn patch(
+        pool: &Pool,
+        actor: &str,
+        id: i32,
+        if_match: &IfMatch,
+        patch: EmployeePatch,
+    ) -> Result<Self, CustomError> {
+        patch.validate()?;
+        let conn = db::connection(pool)?;
+        if let Some(department_id) = patch.department_id {
+            check_department(&conn, department_id)?;
+        }
+        conn.transaction(|| {
+            let before: Employees = employees::table
+                .filter(employees::id.eq(id))
+                .filter(employees::deleted_at.is_null())
+                .first(&conn)?;
+            if_match.check(before.version)?;
+            // diesel refuses an empty SET clause
+            if patch.is_empty() {
+                return Ok(before);
+            }
+            let updated = diesel::update(current(&before))
+                .set((patch, employees::version.eq(employees::version + 1)))
+                .execute(&conn)?;
+            if updated == 0 {
+                return Err(precondition_failed());
+            }
+            let after = employees::table.filter(employees::id.eq(id)).first(&conn)?;
+            audit::record(&conn, actor, Operation::Update, Some(&before), Some(&after))?;
+            Ok(after)
+        })
+    }
+
+    // only marks the employee as deleted, it can be restored until it is purged
+    pub fn delete(pool: &Pool, actor: &str, id: i32, if_match: &IfMatch) -> Result<usize, CustomError> {
+        let conn = db::connection(pool)?;
+        conn.transaction(|| {
+            let before: Employees = employees::table
+                .filter(employees::id.eq(id))
+                .filter(employees::deleted_at.is_null())
+                .first(&conn)?;
+            if_match.check(before.version)?;
+            let deleted = diesel::update(current(&before))
+                .set((
+                    employees::deleted_at.eq(sql::<Nullable<Text>>(&timestamp(0))),
+                    employees::version.eq(employees::version + 1),
+                ))
+                .execute(&conn)?;
+            if deleted == 0 {
+                return Err(precondition_failed());
+            }
+            let after = employees::table.filter(employees::id.eq(id)).first(&conn)?;
+            audit::record(&conn, actor, Operation::Delete, Some(&before), Some(&after))?;
+            Ok(deleted)
+        })
+    }
+
+    pub fn restore(pool: &Pool, actor: &str, id: i32, if_match: &IfMatch) -> Result<Self, CustomError> {
+        let conn = db::connection(pool)?;
+        conn.transaction(|| {
+            let before: Employees = employees::table.filter(employees::id.eq(id)).first(&conn)?;
+            if before.deleted_at.is_none() {
+                return Err(CustomError::new(409, "The employee is not deleted".to_string()));
+            }
+            if_match.check(before.version)?;
+            let restored = diesel::update(current(&before))
+                .set((
+                    employees::deleted_at.eq(None::<String>),
+                    employees::version.eq(employees::version + 1),
+                ))
+                .execute(&conn)?;
+            if restored == 0 {
+                return Err(precondition_failed());
+            }
+            let after = employees::table.filter(employees::id.eq(id)).first(&conn)?;
+            audit::record(&conn, actor, Operation::Restore, Some(&before), Some(&after))?;
+            Ok(after)
+        })
     }
 }
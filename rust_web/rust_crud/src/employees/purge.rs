@@ -0,0 +1,88 @@
+use crate::db::{self, Pool};
+use crate::employees::audit::{self, Operation};
+use crate::employees::{timestamp, Employees};
+use crate::error_handler::CustomError;
+use crate::schema::employees;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{Nullable, Text};
+use log::{error, info};
+use std::time::Duration;
+
+// actor of the audit entries the purge writes
+const PURGE_ACTOR: &str = "retention";
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+impl Employees {
+    // removes the employees deleted more than `retention_days` ago for good
+    pub fn purge(pool: &Pool, retention_days: u32) -> Result<usize, CustomError> {
+        let conn = db::connection(pool)?;
+        conn.transaction(|| {
+            let expired = employees::table
+                .filter(employees::deleted_at.lt(sql::<Nullable<Text>>(&timestamp(retention_days))))
+                .load::<Employees>(&conn)?;
+            for employee in &expired {
+                diesel::delete(employees::table.find(employee.id)).execute(&conn)?;
+                audit::record(&conn, PURGE_ACTOR, Operation::Purge, Some(employee), None)?;
+            }
+            Ok(expired.len())
+        })
+    }
+}
+
+// purges on startup and then every hour, for as long as the server runs
+pub fn spawn_purge(pool: Pool, retention_days: u32) {
+    actix_rt::spawn(async move {
+        let mut interval = actix_rt::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            match db::run(&pool, move |pool| Employees::purge(pool, retention_days)).await {
+                Ok(0) => {}
+                Ok(purged) => info!("purged {} deleted employees", purged),
+                Err(e) => error!("purging deleted employees failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::employees::history;
+    use crate::etag::IfMatch;
+
+    #[test]
+    fn test_purge_keeps_recent_deletions() {
+        let pool = db::test_pool();
+        let mut ids = Vec::new();
+        for name in ["Old", "Recent", "Kept"] {
+            let employee = crate::employees::Employee {
+                first_name: name.to_string(),
+                last_name: "Doe".to_string(),
+                department_id: 1,
+                salary: 1000,
+                age: 30,
+            };
+            ids.push(Employees::create(&pool, "test", employee).unwrap().id);
+        }
+        Employees::delete(&pool, "test", ids[0], &IfMatch::Any).unwrap();
+        Employees::delete(&pool, "test", ids[1], &IfMatch::Any).unwrap();
+        // deleted a month ago
+        diesel::update(employees::table.find(ids[0]))
+            .set(employees::deleted_at.eq(sql::<Nullable<Text>>(&timestamp(31))))
+            .execute(&db::connection(&pool).unwrap())
+            .unwrap();
+
+        assert_eq!(Employees::purge(&pool, 30).unwrap(), 1);
+        assert!(Employees::find_with_deleted(&pool, ids[0]).is_err());
+        assert!(Employees::find_with_deleted(&pool, ids[1]).unwrap().deleted_at.is_some());
+        assert!(Employees::find(&pool, ids[2]).is_ok());
+
+        let (entries, live) = history(&pool, ids[0]).unwrap();
+        assert!(!live);
+        let last = entries.last().unwrap();
+        assert_eq!(last.operation, "purge");
+        assert_eq!(last.actor, PURGE_ACTOR);
+        assert_eq!(Employees::purge(&pool, 30).unwrap(), 0);
+    }
+}
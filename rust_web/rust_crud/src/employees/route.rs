@@ -1,35 +1,1445 @@
-use crate::employees::{Employee, Employees};
-use crate::error_handler::CustomError;
-use actix_web::{delete, get, post, put, web, HttpResponse };
-use serde_json::json;
+use crate::auth::{Claims, Role};
+use crate::db::{self, Pool};
+use crate::departments::Departments;
+use crate::employees::{
+    self, AuditEntry, Employee, EmployeeItem, EmployeePage, EmployeePatch, EmployeeQuery, Employees, Format,
+    ImportSummary, RowError, SearchQuery,
+};
+use crate::error_handler::{CustomError, ErrorResponse};
+use crate::etag::{self, IfMatch};
+use crate::openapi::Deleted;
+use actix_web::http::StatusCode;
+use actix_web::{delete, get, patch, post, put, web, HttpMessage, HttpRequest, HttpResponse };
+use futures::{stream, StreamExt};
+use serde::Deserialize;
+use utoipa::openapi::Deprecated;
+use utoipa::{IntoParams, Modify, OpenApi};
 
+// largest body POST /employees/import accepts
+const IMPORT_LIMIT: usize = 16 * 1024 * 1024;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(find_all, list, main, create, search, import, export, find, update, patch, delete, restore, history),
+    components(schemas(
+        Employee,
+        EmployeePatch,
+        Employees,
+        EmployeeItem,
+        EmployeePage,
+        Departments,
+        AuditEntry,
+        ImportSummary,
+        RowError
+    )),
+    modifiers(&TypoRoute)
+)]
+pub struct EmployeesApi;
+
+// #[deprecated] on the handler would trip over the code actix generates for it
+struct TypoRoute;
+
+impl Modify for TypoRoute {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(operation) = openapi.paths.paths.get_mut("/employeess").and_then(|item| item.get.as_mut()) {
+            operation.deprecated = Some(Deprecated::True);
+        }
+    }
+}
+
+/// The items of GET /employees as a bare array, kept for old clients
+#[utoipa::path(
+    get, path = "/employeess", tag = "employees",
+    params(EmployeeQuery),
+    responses(
+        (status = 200, body = Vec<EmployeeItem>),
+        (status = 304, description = "Not modified"),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+    )
+)]
 #[get("/employeess")]
-async fn find_all() -> Result<HttpResponse, CustomError> {
-    let employees = Employees::find_all()?;
-    Ok(HttpResponse::Ok().json(employees))
+async fn find_all(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    query: web::Query<EmployeeQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let page = page(pool, &user, query).await?;
+    etag::hashed(&req, &user.redact(&page.items)?)
+}
+
+/// A page of employees, salary filters and sorting need hr_editor
+#[utoipa::path(
+    get, path = "/employees", tag = "employees",
+    params(EmployeeQuery),
+    responses(
+        (status = 200, body = EmployeePage),
+        (status = 304, description = "Not modified"),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+#[get("/employees")]
+async fn list(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    query: web::Query<EmployeeQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let page = page(pool, &user, query).await?;
+    etag::hashed(&req, &user.redact(&page)?)
+}
+
+async fn page(
+    pool: web::Data<Pool>,
+    user: &Claims,
+    query: web::Query<EmployeeQuery>,
+) -> Result<EmployeePage, CustomError> {
+    // filtering or sorting on a hidden field would leak it
+    if !user.can_see("salary") && query.uses_salary() {
+        return Err(CustomError::new(403, "Requires the hr_editor role".to_string()));
+    }
+    if query.include_deleted {
+        user.require(Role::Admin)?;
+    }
+    db::run(&pool, move |pool| Employees::find_page(pool, &query)).await
 }
 
+/// Same as GET /employeess
+#[utoipa::path(
+    get, path = "/", tag = "employees",
+    params(EmployeeQuery),
+    responses(
+        (status = 200, body = Vec<EmployeeItem>),
+        (status = 304, description = "Not modified"),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+    )
+)]
 #[get("/")]
-async fn main() -> Result<HttpResponse, CustomError> {
-    let employees = Employees::find_all()?;
-    Ok(HttpResponse::Ok().json(employees))
+async fn main(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    query: web::Query<EmployeeQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let page = page(pool, &user, query).await?;
+    etag::hashed(&req, &user.redact(&page.items)?)
 }
 
+#[utoipa::path(
+    post, path = "/employees", tag = "employees",
+    request_body = Employee,
+    responses(
+        (status = 200, body = Employees, headers(("ETag" = String))),
+        (status = 403, body = ErrorResponse),
+        (status = 422, body = ErrorResponse),
+    )
+)]
 #[post("/employees")]
-async fn create(employee: web::Json<Employee>) -> Result<HttpResponse, CustomError> {
-    let employee = Employees::create(employee.into_inner())?;
-    Ok(HttpResponse::Ok().json(employee))
+async fn create(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    employee: web::Json<Employee>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let actor = user.username.clone();
+    let employee = db::run(&pool, move |pool| {
+        Employees::create(pool, &actor, employee.into_inner())
+    })
+    .await?;
+    etag::versioned(&req, &user, employee.version, &employee)
+}
+
+/// Employees whose names start like the words of q, forgiving typos
+#[utoipa::path(
+    get, path = "/employees/search", tag = "employees",
+    params(SearchQuery),
+    responses(
+        (status = 200, body = Vec<Employees>),
+        (status = 304, description = "Not modified"),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+#[get("/employees/search")]
+async fn search(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    query: web::Query<SearchQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let employees = db::run(&pool, move |pool| Employees::search(pool, &query)).await?;
+    etag::hashed(&req, &user.redact(&employees)?)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ImportQuery {
+    // overrides the Content-Type, csv or ndjson
+    format: Option<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+// the whole body is needed anyway, the import is a single transaction
+async fn read_body(mut payload: web::Payload) -> Result<Vec<u8>, CustomError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| CustomError::new(400, format!("Failed reading body: {}", e)))?;
+        if body.len() + chunk.len() > IMPORT_LIMIT {
+            return Err(CustomError::new(413, format!("Imports are limited to {} bytes", IMPORT_LIMIT)));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Insert all rows of a csv or ndjson body, or none of them
+#[utoipa::path(
+    post, path = "/employees/import", tag = "employees",
+    params(ImportQuery),
+    request_body(content((String = "text/csv"), (String = "application/x-ndjson"))),
+    responses(
+        (status = 200, body = ImportSummary),
+        (status = 403, body = ErrorResponse),
+        (status = 413, body = ErrorResponse),
+        (status = 415, body = ErrorResponse),
+        (status = 422, description = "Some rows are invalid, nothing was imported", body = ImportSummary),
+    )
+)]
+#[post("/employees/import")]
+async fn import(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    query: web::Query<ImportQuery>,
+    payload: web::Payload,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let format = match &query.format {
+        Some(format) => Format::parse(format)?,
+        None => Format::from_mime(req.content_type()).ok_or_else(|| {
+            CustomError::new(415, "Send text/csv or application/x-ndjson".to_string())
+        })?,
+    };
+    let body = read_body(payload).await?;
+    let actor = user.username.clone();
+    let dry_run = query.dry_run;
+    let summary = db::run(&pool, move |pool| {
+        Employees::import(pool, &actor, employees::parse(format, &body), dry_run)
+    })
+    .await?;
+    let status = if dry_run || summary.errors.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+    Ok(HttpResponse::build(status).json(summary))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ExportQuery {
+    // csv or ndjson, csv by default
+    format: Option<String>,
+}
+
+// streamed batch by batch, the table is never held in memory at once
+/// Every employee as csv or ndjson, salaries are left out for viewers
+#[utoipa::path(
+    get, path = "/employees/export", tag = "employees",
+    params(ExportQuery),
+    responses(
+        (status = 200, content((String = "text/csv"), (String = "application/x-ndjson"))),
+        (status = 400, body = ErrorResponse),
+    )
+)]
+#[get("/employees/export")]
+async fn export(
+    pool: web::Data<Pool>,
+    user: Claims,
+    query: web::Query<ExportQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let format = Format::parse(query.format.as_deref().unwrap_or("csv"))?;
+    let header = employees::export_header(format, &user)?;
+    let rows = stream::unfold(Some(i32::MIN), move |after_id| {
+        let pool = pool.clone();
+        let user = user.clone();
+        async move {
+            let after_id = after_id?;
+            let batch = match db::run(&pool, move |pool| Employees::export_batch(pool, after_id)).await {
+                Ok(batch) => batch,
+                Err(e) => return Some((Err(e), None)),
+            };
+            let last_id = batch.last()?.id;
+            let next = if batch.len() as i64 == employees::EXPORT_BATCH_SIZE {
+                Some(last_id)
+            } else {
+                None
+            };
+            Some((employees::export_rows(format, &user, &batch), next))
+        }
+    });
+    let body = stream::iter(header.map(Ok)).chain(rows);
+    Ok(HttpResponse::Ok()
+        .content_type(format.content_type())
+        .streaming(body.boxed_local()))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FindQuery {
+    // admins only
+    #[serde(default)]
+    include_deleted: bool,
 }
 
+#[utoipa::path(
+    get, path = "/employees/{id}", tag = "employees",
+    params(("id" = i32, Path), FindQuery),
+    responses(
+        (status = 200, body = Employees, headers(("ETag" = String, description = "The version of the employee"))),
+        (status = 304, description = "Not modified"),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
 #[get("/employees/{id}")]
-async fn find(id: web::Path<i32>) -> Result<HttpResponse, CustomError> {
-    let employee = Employees::find(id.into_inner())?;
-    Ok(HttpResponse::Ok().json(employee))
+async fn find(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    id: web::Path<i32>,
+    query: web::Query<FindQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let id = id.into_inner();
+    let employee = if query.include_deleted {
+        user.require(Role::Admin)?;
+        db::run(&pool, move |pool| Employees::find_with_deleted(pool, id)).await?
+    } else {
+        db::run(&pool, move |pool| Employees::find(pool, id)).await?
+    };
+    etag::versioned(&req, &user, employee.version, &employee)
+}
+
+/// Replace an employee
+#[utoipa::path(
+    put, path = "/employees/{id}", tag = "employees",
+    params(("id" = i32, Path), ("If-Match" = String, Header, description = "ETag of the employee, or *")),
+    request_body = Employee,
+    responses(
+        (status = 200, body = Employees, headers(("ETag" = String))),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 412, description = "Changed since it was read", body = ErrorResponse),
+        (status = 422, body = ErrorResponse),
+        (status = 428, description = "If-Match is missing", body = ErrorResponse),
+    )
+)]
+#[put("/employees/{id}")]
+async fn update(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    id: web::Path<i32>,
+    employee: web::Json<Employee>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let if_match = IfMatch::required(&req)?;
+    let actor = user.username.clone();
+    let employee = db::run(&pool, move |pool| {
+        Employees::update(pool, &actor, id.into_inner(), &if_match, employee.into_inner())
+    })
+    .await?;
+    etag::versioned(&req, &user, employee.version, &employee)
+}
+
+/// Change the given fields of an employee
+#[utoipa::path(
+    patch, path = "/employees/{id}", tag = "employees",
+    params(("id" = i32, Path), ("If-Match" = String, Header, description = "ETag of the employee, or *")),
+    request_body = EmployeePatch,
+    responses(
+        (status = 200, body = Employees, headers(("ETag" = String))),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 412, description = "Changed since it was read", body = ErrorResponse),
+        (status = 422, body = ErrorResponse),
+        (status = 428, description = "If-Match is missing", body = ErrorResponse),
+    )
+)]
+#[patch("/employees/{id}")]
+async fn patch(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    id: web::Path<i32>,
+    employee: web::Json<EmployeePatch>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let if_match = IfMatch::required(&req)?;
+    let actor = user.username.clone();
+    let employee = db::run(&pool, move |pool| {
+        Employees::patch(pool, &actor, id.into_inner(), &if_match, employee.into_inner())
+    })
+    .await?;
+    etag::versioned(&req, &user, employee.version, &employee)
+}
+
+/// Mark an employee deleted, it is purged after the retention period
+#[utoipa::path(
+    delete, path = "/employees/{id}", tag = "employees",
+    params(("id" = i32, Path), ("If-Match" = Option<String>, Header, description = "ETag of the employee")),
+    responses(
+        (status = 200, body = Deleted),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 412, description = "Changed since it was read", body = ErrorResponse),
+    )
+)]
+#[delete("/employees/{id}")]
+async fn delete(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    // optional here, deleting needs no knowledge of the current fields
+    let if_match = IfMatch::optional(&req);
+    let actor = user.username.clone();
+    let deleted = db::run(&pool, move |pool| {
+        Employees::delete(pool, &actor, id.into_inner(), &if_match)
+    })
+    .await?;
+    Ok(HttpResponse::Ok().json(Deleted { deleted }))
+}
+
+/// Undo the delete of an employee that is not purged yet
+#[utoipa::path(
+    post, path = "/employees/{id}/restore", tag = "employees",
+    params(("id" = i32, Path), ("If-Match" = Option<String>, Header, description = "ETag of the employee")),
+    responses(
+        (status = 200, body = Employees, headers(("ETag" = String))),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "The employee is not deleted", body = ErrorResponse),
+        (status = 412, description = "Changed since it was read", body = ErrorResponse),
+    )
+)]
+#[post("/employees/{id}/restore")]
+async fn restore(
+    pool: web::Data<Pool>,
+    user: Claims,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    let if_match = IfMatch::optional(&req);
+    let actor = user.username.clone();
+    let employee = db::run(&pool, move |pool| {
+        Employees::restore(pool, &actor, id.into_inner(), &if_match)
+    })
+    .await?;
+    etag::versioned(&req, &user, employee.version, &employee)
+}
+
+/// Every change to an employee, oldest first, admin only once it is deleted
+#[utoipa::path(
+    get, path = "/employees/{id}/history", tag = "employees",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Vec<AuditEntry>),
+        (status = 403, body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+#[get("/employees/{id}/history")]
+async fn history(
+    pool: web::Data<Pool>,
+    user: Claims,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, CustomError> {
+    let (entries, live) = db::run(&pool, move |pool| employees::history(pool, id.into_inner())).await?;
+    // deleted and purged employees are only shown to admins, like include_deleted
+    if !live {
+        user.require(Role::Admin)?;
+    }
+    Ok(HttpResponse::Ok().json(user.redact(&entries)?))
 }
 
 pub fn init_routes(config: &mut web::ServiceConfig) {
     config.service(find_all);
+    config.service(list);
+    // before /employees/{id}, which would take "export" for an id
+    config.service(import);
+    config.service(export);
+    config.service(search);
     config.service(find);
+    config.service(history);
     config.service(main);
     config.service(create);
+    config.service(update);
+    config.service(patch);
+    config.service(delete);
+    config.service(restore);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::testing::{bearer, test_app};
+    use crate::db;
+    use crate::departments::{Department, Departments};
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    // department 1 is "Dept", seeded by the migrations
+    fn employee() -> Value {
+        json!({
+            "first_name": "Jane",
+            "last_name": "Doe",
+            "department_id": 1,
+            "salary": 4200,
+            "age": 35
+        })
+    }
+
+    fn create_employee(pool: &Pool) -> i32 {
+        let employee = Employees::create(pool, "test", serde_json::from_value(employee()).unwrap()).unwrap();
+        employee.id
+    }
+
+    #[actix_rt::test]
+    async fn test_create_employee() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees")
+            .set_json(&employee())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let created: Value = test::read_body_json(resp).await;
+        assert_eq!(created["first_name"], json!("Jane"));
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}", created["id"]))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let found: Value = test::read_body_json(resp).await;
+        assert_eq!(found, created);
+    }
+
+    #[actix_rt::test]
+    async fn test_find_missing_employee_is_404() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees/999999")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let error: Value = test::read_body_json(resp).await;
+        assert!(error["message"].is_string());
+    }
+
+    #[actix_rt::test]
+    async fn test_find_all_employees() {
+        let pool = db::test_pool();
+        create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        // the initial migration seeds an admin row, both answer the items of GET /employees
+        for (uri, count) in [("/", 2), ("/employeess", 2), ("/employeess?limit=1", 1)].iter() {
+            let req = test::TestRequest::get()
+                .header("Authorization", bearer(Role::HrEditor))
+                .uri(uri)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+            let employees: Value = test::read_body_json(resp).await;
+            assert_eq!(employees.as_array().unwrap().len(), *count);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_list_employees_paged() {
+        let pool = db::test_pool();
+        for _ in 0..3 {
+            create_employee(&pool);
+        }
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees?limit=3")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], json!(4));
+        assert_eq!(page["items"].as_array().unwrap().len(), 3);
+        let cursor = page["next_cursor"].as_i64().unwrap();
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees?limit=3&cursor={}", cursor))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["items"].as_array().unwrap().len(), 1);
+        assert_eq!(page["next_cursor"], Value::Null);
+
+        // an exactly full last page has no next page either
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees?limit=4")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["items"].as_array().unwrap().len(), 4);
+        assert_eq!(page["next_cursor"], Value::Null);
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees?department=dept&sort=salary:desc")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], json!(4));
+        assert!(page["items"][0].get("department").is_none());
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees?limit=1&embed=department")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["items"][0]["department"], json!({ "id": 1, "name": "Dept" }));
+    }
+
+    #[actix_rt::test]
+    async fn test_history_records_every_change() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees")
+            .set_json(&employee())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let created: Value = test::read_body_json(resp).await;
+        let id = created["id"].as_i64().unwrap();
+
+        for body in [json!({ "salary": 5000 }), json!({ "salary": 5000 }), json!({})].iter() {
+            let req = test::TestRequest::patch()
+                .header("Authorization", bearer(Role::HrEditor))
+                .header("If-Match", "*")
+                .uri(&format!("/employees/{}", id))
+                .set_json(body)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::Admin))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // a deleted employee's history is admin only
+        for role in [Role::Viewer, Role::HrEditor] {
+            let req = test::TestRequest::get()
+                .header("Authorization", bearer(role))
+                .uri(&format!("/employees/{}/history", id))
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        }
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Admin))
+            .uri(&format!("/employees/{}/history", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let entries: Value = test::read_body_json(resp).await;
+        let entries = entries.as_array().unwrap();
+        // the repeated and the empty patch changed nothing
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0]["operation"], json!("create"));
+        assert_eq!(entries[0]["actor"], json!("hr_editor"));
+        assert_eq!(entries[0]["changes"]["first_name"], json!({ "before": null, "after": "Jane" }));
+        assert_eq!(entries[1]["operation"], json!("update"));
+        assert_eq!(entries[1]["changes"], json!({ "salary": { "before": 4200, "after": 5000 } }));
+        assert_eq!(entries[2]["operation"], json!("delete"));
+        assert_eq!(entries[2]["actor"], json!("admin"));
+        // a soft delete only sets deleted_at
+        let changes = entries[2]["changes"].as_object().unwrap();
+        assert_eq!(changes.keys().collect::<Vec<_>>(), vec!["deleted_at"]);
+        assert_eq!(changes["deleted_at"]["before"], json!(null));
+        assert!(changes["deleted_at"]["after"].is_string());
+
+        // viewers read the history of a live employee, without the salary
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}/restore", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri(&format!("/employees/{}/history", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let entries: Value = test::read_body_json(resp).await;
+        assert_eq!(entries[1]["changes"], json!({}));
+        assert!(entries[0]["changes"].get("salary").is_none());
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees/999999/history")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_viewer_cannot_see_salary() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let employee: Value = test::read_body_json(resp).await;
+        assert_eq!(employee["first_name"], json!("Jane"));
+        assert!(employee.get("salary").is_none());
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri("/employees")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let page: Value = test::read_body_json(resp).await;
+        assert!(page["items"].as_array().unwrap().iter().all(|e| e.get("salary").is_none()));
+
+        for uri in ["/employees?min_salary=4000", "/employees?sort=salary:desc"].iter() {
+            let req = test::TestRequest::get()
+                .header("Authorization", bearer(Role::Viewer))
+                .uri(uri)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", uri);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_viewer_cannot_modify() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri("/employees")
+            .set_json(&employee())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let req = test::TestRequest::get()
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[actix_rt::test]
+    async fn test_list_employees_bad_query_is_400() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        for uri in [
+            "/employees?sort=salary:up",
+            "/employees?limit=0",
+            "/employees?limit=x",
+            "/employees?embed=manager",
+        ].iter() {
+            let req = test::TestRequest::get()
+                .header("Authorization", bearer(Role::HrEditor))
+                .uri(uri)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_put_replaces_employee() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let hr = Departments::create(&pool, Department { name: "HR".to_string() }).unwrap();
+        let mut app = test_app(&pool).await;
+
+        let mut body = employee();
+        body["department_id"] = json!(hr.id);
+        body["salary"] = json!(5000);
+        let req = test::TestRequest::put()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let updated: Value = test::read_body_json(resp).await;
+        assert_eq!(updated["id"], json!(id));
+        assert_eq!(updated["department_id"], json!(hr.id));
+        assert_eq!(updated["salary"], json!(5000));
+    }
+
+    #[actix_rt::test]
+    async fn test_put_missing_employee_is_404() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        let req = test::TestRequest::put()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri("/employees/999999")
+            .set_json(&employee())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_put_incomplete_body_is_400() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+        let req = test::TestRequest::put()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({ "first_name": "Jane" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_create_invalid_employee_is_422() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let mut body = employee();
+        body["first_name"] = json!("");
+        body["salary"] = json!(-1);
+        body["age"] = json!(500);
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees")
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let error: Value = test::read_body_json(resp).await;
+        let fields = error["errors"].as_object().unwrap();
+        let mut names: Vec<&str> = fields.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["age", "first_name", "salary"]);
+        assert_eq!(error["errors"]["salary"], json!(["must not be negative"]));
+    }
+
+    #[actix_rt::test]
+    async fn test_put_invalid_employee_is_422() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        let mut body = employee();
+        body["age"] = json!(0);
+        let req = test::TestRequest::put()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&body)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let error: Value = test::read_body_json(resp).await;
+        assert!(error["errors"]["age"].is_array());
+
+        let stored = Employees::find(&pool, id).unwrap();
+        assert_eq!(stored.age, 35);
+    }
+
+    #[actix_rt::test]
+    async fn test_patch_invalid_field_is_422() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+        let req = test::TestRequest::patch()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({ "last_name": "" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let error: Value = test::read_body_json(resp).await;
+        assert!(error["errors"]["last_name"].is_array());
+
+        let req = test::TestRequest::patch()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({ "department_id": 999 }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let error: Value = test::read_body_json(resp).await;
+        assert_eq!(error["errors"]["department_id"], json!(["unknown department"]));
+    }
+
+    #[actix_rt::test]
+    async fn test_patch_updates_given_fields() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::patch()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({ "age": 36 }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let patched: Value = test::read_body_json(resp).await;
+        assert_eq!(patched["age"], json!(36));
+        assert_eq!(patched["first_name"], json!("Jane"));
+        assert_eq!(patched["salary"], json!(4200));
+
+        let req = test::TestRequest::patch()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({}))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let unchanged: Value = test::read_body_json(resp).await;
+        assert_eq!(unchanged["age"], json!(36));
+    }
+
+    #[actix_rt::test]
+    async fn test_patch_missing_employee_is_404() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        for body in [json!({ "age": 36 }), json!({})].iter() {
+            let req = test::TestRequest::patch()
+                .header("Authorization", bearer(Role::HrEditor))
+                .header("If-Match", "*")
+                .uri("/employees/999999")
+                .set_json(body)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_patch_wrong_type_is_400() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+        let req = test::TestRequest::patch()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({ "salary": "a lot" }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_delete_employee() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["deleted"], json!(1));
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_rt::test]
+    async fn test_restore_deleted_employee() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], json!(1));
+
+        // only admins see deleted employees
+        for uri in ["/employees?include_deleted=true".to_string(), format!("/employees/{}?include_deleted=true", id)] {
+            let req = test::TestRequest::get()
+                .header("Authorization", bearer(Role::HrEditor))
+                .uri(&uri)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        }
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Admin))
+            .uri("/employees?include_deleted=true")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], json!(2));
+        assert!(page["items"][1]["deleted_at"].is_string());
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Admin))
+            .uri(&format!("/employees/{}?include_deleted=true", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let tag = resp.headers().get("ETag").unwrap().to_str().unwrap().to_string();
+
+        // updates don't reach deleted employees
+        let req = test::TestRequest::patch()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "*")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({ "salary": 1 }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", tag.as_str())
+            .uri(&format!("/employees/{}/restore", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let restored: Value = test::read_body_json(resp).await;
+        assert!(restored.get("deleted_at").is_none());
+        assert_eq!(restored["salary"], json!(4200));
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}/restore", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}/history", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let entries: Value = test::read_body_json(resp).await;
+        assert_eq!(entries[2]["operation"], json!("restore"));
+        assert_eq!(entries[2]["changes"]["deleted_at"]["after"], json!(null));
+    }
+
+    #[actix_rt::test]
+    async fn test_import_csv_then_export() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let csv = "first_name,last_name,department_id,salary,age\n\
+                   Ada, Lovelace ,1,5000,36\n\
+                   \"Hopper, Grace\",Hopper,1,5100,40\n";
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("Content-Type", "text/csv")
+            .uri("/employees/import")
+            .set_payload(csv)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let summary: Value = test::read_body_json(resp).await;
+        assert_eq!(summary, json!({ "dry_run": false, "rows": 2, "imported": 2, "errors": [] }));
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees/export?format=csv")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        let lines: Vec<&str> = std::str::from_utf8(&body).unwrap().lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "id,first_name,last_name,department_id,salary,age");
+        assert!(lines[2].ends_with(",Ada,Lovelace,1,5000,36"));
+        assert!(lines[3].ends_with(",\"Hopper, Grace\",Hopper,1,5100,40"));
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees/export?format=ndjson")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let body = test::read_body(resp).await;
+        let rows: Vec<Value> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[2]["first_name"], json!("Hopper, Grace"));
+
+        // every imported row is in the audit history
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}/history", rows[1]["id"]))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let entries: Value = test::read_body_json(resp).await;
+        assert_eq!(entries[0]["operation"], json!("create"));
+    }
+
+    #[actix_rt::test]
+    async fn test_import_with_invalid_rows() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let ndjson = [
+            employee().to_string(),
+            String::new(),
+            json!({ "first_name": "", "last_name": "Doe", "department_id": 1, "salary": 1, "age": 30 }).to_string(),
+            json!({ "first_name": "Jo", "last_name": "Doe", "department_id": 999, "salary": 1, "age": 30 }).to_string(),
+            "{ not json".to_string(),
+        ]
+        .join("\n");
+        for dry_run in [true, false] {
+            let req = test::TestRequest::post()
+                .header("Authorization", bearer(Role::HrEditor))
+                .header("Content-Type", "application/x-ndjson")
+                .uri(&format!("/employees/import?dry_run={}", dry_run))
+                .set_payload(ndjson.clone())
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            let expected = if dry_run { StatusCode::OK } else { StatusCode::UNPROCESSABLE_ENTITY };
+            assert_eq!(resp.status(), expected);
+            let summary: Value = test::read_body_json(resp).await;
+            assert_eq!(summary["rows"], json!(4));
+            assert_eq!(summary["imported"], json!(0));
+            let errors = summary["errors"].as_array().unwrap();
+            assert_eq!(errors.len(), 3);
+            assert_eq!(errors[0]["line"], json!(3));
+            assert_eq!(errors[0]["errors"]["first_name"], json!(["must be 1 to 100 characters"]));
+            assert_eq!(errors[1]["errors"]["department_id"], json!(["unknown department"]));
+            assert_eq!(errors[2]["line"], json!(5));
+        }
+        // only the seeded employee, nothing was imported
+        let page = Employees::find_page(&pool, &EmployeeQuery::default()).unwrap();
+        assert_eq!(page.total, 1);
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("Content-Type", "application/json")
+            .uri("/employees/import")
+            .set_payload(employee().to_string())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[actix_rt::test]
+    async fn test_viewer_export_hides_salary() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri("/employees/export")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = test::read_body(resp).await;
+        assert_eq!(&body[..], &b"id,first_name,last_name,department_id,age\n0,Mr,Admin,1,30\n"[..]);
+
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::Viewer))
+            .header("Content-Type", "text/csv")
+            .uri("/employees/import")
+            .set_payload("first_name,last_name,department_id,salary,age\n")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[actix_rt::test]
+    async fn test_export_spans_batches() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        let rows = employees::EXPORT_BATCH_SIZE as usize + 100;
+        let ndjson = vec![employee().to_string(); rows].join("\n");
+        let req = test::TestRequest::post()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees/import?format=ndjson")
+            .set_payload(ndjson)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/employees/export?format=ndjson")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let body = test::read_body(resp).await;
+        let ids: Vec<i64> = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["id"].as_i64().unwrap())
+            .collect();
+        // the seeded employee plus the imported ones, each exactly once
+        assert_eq!(ids.len(), rows + 1);
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[actix_rt::test]
+    async fn test_search_employees() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        let mut ids = Vec::new();
+        for (first_name, last_name) in [("Janet", "Smith"), ("John", "Smyth"), ("Jane", "Doe"), ("Éva", "Kovács")] {
+            let employee = Employee {
+                first_name: first_name.to_string(),
+                last_name: last_name.to_string(),
+                department_id: 1,
+                salary: 1000,
+                age: 30,
+            };
+            ids.push(Employees::create(&pool, "test", employee).unwrap().id);
+        }
+
+        // last names found for q, in order
+        macro_rules! search_names {
+            ($q:expr) => {{
+                let req = test::TestRequest::get()
+                    .header("Authorization", bearer(Role::Viewer))
+                    .uri(&format!("/employees/search?q={}", $q))
+                    .to_request();
+                let resp = test::call_service(&mut app, req).await;
+                assert_eq!(resp.status(), StatusCode::OK);
+                let found: Value = test::read_body_json(resp).await;
+                found
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|employee| {
+                        assert!(employee.get("salary").is_none());
+                        employee["last_name"].as_str().unwrap().to_string()
+                    })
+                    .collect::<Vec<_>>()
+            }};
+        }
+
+        // prefix and case
+        let mut found = search_names!("JAN");
+        found.sort();
+        assert_eq!(found, vec!["Doe", "Smith"]);
+        // exact matches rank above the one typo away
+        assert_eq!(search_names!("smith"), vec!["Smith", "Smyth"]);
+        assert_eq!(search_names!("jaen%20do"), vec!["Doe"]);
+        assert!(search_names!("zzz").is_empty());
+        // diacritics are kept on both backends
+        assert_eq!(search_names!("%C3%A9va"), vec!["Kovács"]);
+        assert_eq!(search_names!("kov%C3%A1"), vec!["Kovács"]);
+
+        // the index follows updates and deletes
+        let rename = EmployeePatch {
+            last_name: Some("Dorsey".to_string()),
+            ..EmployeePatch::default()
+        };
+        Employees::patch(&pool, "test", ids[0], &IfMatch::Any, rename).unwrap();
+        Employees::delete(&pool, "test", ids[2], &IfMatch::Any).unwrap();
+        assert_eq!(search_names!("dor"), vec!["Dorsey"]);
+        assert_eq!(search_names!("smith"), vec!["Smyth"]);
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri("/employees/search?q=%20-")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_concurrent_updates_need_the_current_etag() {
+        let pool = db::test_pool();
+        let id = create_employee(&pool);
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"1\"");
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-None-Match", "\"1\"")
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        // a viewer gets the body without the salary, under a tag of its own
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .header("If-None-Match", "\"1\"")
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"1-viewer\"");
+        assert_eq!(resp.headers().get("Vary").unwrap(), "Authorization");
+
+        let req = test::TestRequest::put()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri(&format!("/employees/{}", id))
+            .set_json(&employee())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_REQUIRED);
+
+        // the first editor wins
+        let req = test::TestRequest::patch()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "\"1\"")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&json!({ "salary": 5000 }))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("ETag").unwrap(), "\"2\"");
+        let patched: Value = test::read_body_json(resp).await;
+        assert_eq!(patched["version"], json!(2));
+
+        // the second one still holds version 1
+        let req = test::TestRequest::put()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "\"1\"")
+            .uri(&format!("/employees/{}", id))
+            .set_json(&employee())
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(Employees::find(&pool, id).unwrap().salary, 5000);
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "W/\"2\"")
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        let req = test::TestRequest::delete()
+            .header("Authorization", bearer(Role::HrEditor))
+            .header("If-Match", "\"1\", \"2\"")
+            .uri(&format!("/employees/{}", id))
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_rt::test]
+    async fn test_list_not_modified() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .uri("/employees")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let tag = resp.headers().get("ETag").unwrap().to_str().unwrap().to_string();
+        assert!(tag.starts_with("W/"));
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .header("If-None-Match", tag.as_str())
+            .uri("/employees")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get("ETag").unwrap().to_str().unwrap(), tag);
+
+        create_employee(&pool);
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::Viewer))
+            .header("If-None-Match", tag.as_str())
+            .uri("/employees")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let page: Value = test::read_body_json(resp).await;
+        assert_eq!(page["total"], json!(2));
+    }
 }
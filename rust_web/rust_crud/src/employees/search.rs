@@ -0,0 +1,258 @@
+use crate::db::{self, Pool};
+use crate::employees::Employees;
+use crate::error_handler::CustomError;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Double, Integer, Text};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+const DEFAULT_SEARCH_LIMIT: i64 = 10;
+const MAX_SEARCH_LIMIT: i64 = 50;
+const MAX_TERMS: usize = 5;
+// indexed words tried for a single query term
+const MAX_CANDIDATES: usize = 50;
+// rows ranked by the database before typos are weighed in
+const RANKED_PER_RESULT: i64 = 5;
+
+// sqlite: an fts5 table over the names, kept in sync by triggers
+#[cfg(not(feature = "postgres"))]
+const TERMS_SQL: &str = "SELECT term FROM employee_search_terms WHERE term >= ? AND term < ?";
+#[cfg(not(feature = "postgres"))]
+const SEARCH_SQL: &str = "SELECT e.id, e.first_name, e.last_name, e.department_id, e.salary, e.age, e.version, \
+                          -bm25(employee_search) AS score \
+                          FROM employee_search JOIN employees e ON e.id = employee_search.rowid \
+                          WHERE employee_search MATCH ? AND e.deleted_at IS NULL ORDER BY score DESC, e.id LIMIT ?";
+
+// postgres: a generated tsvector column with a gin index, the words kept in a table by a trigger
+#[cfg(feature = "postgres")]
+const TERMS_SQL: &str = "SELECT term FROM employee_search_terms WHERE term >= $1 AND term < $2";
+#[cfg(feature = "postgres")]
+const SEARCH_SQL: &str = "SELECT id, first_name, last_name, department_id, salary, age, version, \
+                          ts_rank(search, to_tsquery('simple', $1))::float8 AS score \
+                          FROM employees WHERE search @@ to_tsquery('simple', $1) AND deleted_at IS NULL \
+                          ORDER BY score DESC, id LIMIT $2";
+
+// query string of GET /employees/search
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(QueryableByName)]
+struct Term {
+    #[sql_type = "Text"]
+    term: String,
+}
+
+#[derive(QueryableByName)]
+struct Hit {
+    #[sql_type = "Integer"]
+    id: i32,
+    #[sql_type = "Text"]
+    first_name: String,
+    #[sql_type = "Text"]
+    last_name: String,
+    #[sql_type = "Integer"]
+    department_id: i32,
+    #[sql_type = "Integer"]
+    salary: i32,
+    #[sql_type = "Integer"]
+    age: i32,
+    #[sql_type = "Integer"]
+    version: i32,
+    #[sql_type = "Double"]
+    score: f64,
+}
+
+impl From<Hit> for Employees {
+    fn from(hit: Hit) -> Employees {
+        Employees {
+            id: hit.id,
+            first_name: hit.first_name,
+            last_name: hit.last_name,
+            department_id: hit.department_id,
+            salary: hit.salary,
+            age: hit.age,
+            version: hit.version,
+            deleted_at: None,
+        }
+    }
+}
+
+// lowercase words, split like the default tokenizers of both backends
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// typos forgiven in a term, none for the first letters typed, the very first is never taken for one
+fn allowed_typos(term: &str) -> usize {
+    match term.chars().count() {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+// edit distance between term and the closest prefix of word, so "jna" is one typo from "janet"
+fn prefix_distance(term: &str, word: &str) -> usize {
+    let word: Vec<char> = word.chars().collect();
+    let mut row: Vec<usize> = (0..=word.len()).collect();
+    for (i, t) in term.chars().enumerate() {
+        let mut next = vec![i + 1; word.len() + 1];
+        for (j, w) in word.iter().enumerate() {
+            let substitution = row[j] + usize::from(t != *w);
+            next[j + 1] = substitution.min(row[j + 1] + 1).min(next[j] + 1);
+        }
+        row = next;
+    }
+    row.into_iter().min().unwrap_or(0)
+}
+
+// the indexed words starting with the first letter of term, the range is in code point order
+fn first_letter_range(term: &str) -> (String, String) {
+    let first = term.chars().next().unwrap_or_default();
+    let next = (u32::from(first) + 1..).find_map(char::from_u32).unwrap_or(char::MAX);
+    (first.to_string(), next.to_string())
+}
+
+// the indexed words a query term may stand for, closest first
+fn candidates<'a>(term: &str, vocabulary: &'a [String]) -> Vec<&'a str> {
+    let allowed = allowed_typos(term);
+    let mut matches: Vec<(usize, &str)> = vocabulary
+        .iter()
+        .map(|word| (prefix_distance(term, word), word.as_str()))
+        .filter(|(distance, _)| *distance <= allowed)
+        .collect();
+    matches.sort_by_key(|(distance, word)| (*distance, word.len()));
+    matches.truncate(MAX_CANDIDATES);
+    matches.into_iter().map(|(_, word)| word).collect()
+}
+
+// every term has to match one of its candidates
+#[cfg(not(feature = "postgres"))]
+fn match_expression(candidates: &[Vec<&str>]) -> String {
+    candidates
+        .iter()
+        .map(|words| {
+            let words: Vec<String> = words.iter().map(|word| format!("\"{}\"", word.replace('"', "\"\""))).collect();
+            format!("({})", words.join(" OR "))
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+#[cfg(feature = "postgres")]
+fn match_expression(candidates: &[Vec<&str>]) -> String {
+    candidates
+        .iter()
+        .map(|words| {
+            let words: Vec<String> = words
+                .iter()
+                .map(|word| format!("'{}'", word.replace('\\', "\\\\").replace('\'', "''")))
+                .collect();
+            format!("({})", words.join(" | "))
+        })
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+// typos needed for the names of an employee to match every term
+fn typos(terms: &[String], employee: &Employees) -> usize {
+    let mut names = words(&employee.first_name);
+    names.extend(words(&employee.last_name));
+    terms
+        .iter()
+        .map(|term| names.iter().map(|name| prefix_distance(term, name)).min().unwrap_or(0))
+        .sum()
+}
+
+impl Employees {
+    // prefix, case-insensitive and typo-tolerant matching on first and last name, the
+    // closest matches first and the database's relevance rank between equally close ones
+    pub fn search(pool: &Pool, query: &SearchQuery) -> Result<Vec<Self>, CustomError> {
+        let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+        if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+            return Err(CustomError::new(400, format!("limit must be between 1 and {}", MAX_SEARCH_LIMIT)));
+        }
+        let mut terms = words(&query.q);
+        if terms.is_empty() {
+            return Err(CustomError::new(400, "q needs at least one letter or digit".to_string()));
+        }
+        terms.truncate(MAX_TERMS);
+
+        let conn = db::connection(pool)?;
+        let vocabularies = terms
+            .iter()
+            .map(|term| {
+                let (from, to) = first_letter_range(term);
+                let words = sql_query(TERMS_SQL)
+                    .bind::<Text, _>(from)
+                    .bind::<Text, _>(to)
+                    .load::<Term>(&conn)?;
+                Ok(words.into_iter().map(|word| word.term).collect())
+            })
+            .collect::<Result<Vec<Vec<String>>, CustomError>>()?;
+        let candidates: Vec<Vec<&str>> = terms
+            .iter()
+            .zip(&vocabularies)
+            .map(|(term, vocabulary)| candidates(term, vocabulary))
+            .collect();
+        if candidates.iter().any(Vec::is_empty) {
+            return Ok(Vec::new());
+        }
+
+        let hits = sql_query(SEARCH_SQL)
+            .bind::<Text, _>(match_expression(&candidates))
+            .bind::<BigInt, _>(limit * RANKED_PER_RESULT)
+            .load::<Hit>(&conn)?;
+        let mut ranked: Vec<(usize, f64, Employees)> = hits
+            .into_iter()
+            .map(|hit| {
+                let score = hit.score;
+                let employee = Employees::from(hit);
+                (typos(&terms, &employee), score, employee)
+            })
+            .collect();
+        // stable, ties keep the order of the database
+        ranked.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)));
+        ranked.truncate(limit as usize);
+        Ok(ranked.into_iter().map(|(_, _, employee)| employee).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix_distance() {
+        assert_eq!(prefix_distance("jan", "janet"), 0);
+        assert_eq!(prefix_distance("jna", "janet"), 1);
+        assert_eq!(prefix_distance("jonh", "johnson"), 1);
+        assert_eq!(prefix_distance("smiht", "smith"), 1);
+        assert_eq!(prefix_distance("smith", "smyth"), 1);
+        assert_eq!(prefix_distance("", "anything"), 0);
+    }
+
+    #[test]
+    fn test_candidates() {
+        let vocabulary: Vec<String> = ["janet", "jane", "jean", "john", "doe"].iter().map(|w| w.to_string()).collect();
+        assert_eq!(candidates("jan", &vocabulary), vec!["jane", "janet", "jean"]);
+        assert_eq!(candidates("ja", &vocabulary), vec!["jane", "janet"]);
+        assert_eq!(candidates("xyz", &vocabulary), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_first_letter_range() {
+        assert_eq!(first_letter_range("jan"), ("j".to_string(), "k".to_string()));
+        assert_eq!(first_letter_range("éva"), ("é".to_string(), "ê".to_string()));
+        // the surrogates are no chars, the range skips them
+        assert_eq!(first_letter_range("\u{d7ff}"), ("\u{d7ff}".to_string(), "\u{e000}".to_string()));
+    }
+}
@@ -1,14 +1,29 @@
+use actix_web::error::BlockingError;
 use actix_web::http::StatusCode;
 use actix_web::{HttpResponse, ResponseError};
 use diesel::result::Error as DieselError;
-use serde::Deserialize;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
+use utoipa::ToSchema;
+use validator::ValidationErrors;
 
 #[derive(Debug, Deserialize)]
 pub struct CustomError {
     pub error_status_code: u16,
     pub error_message: String,
+    // per-field messages of a rejected payload
+    #[serde(default)]
+    pub field_errors: BTreeMap<String, Vec<String>>,
+}
+
+// the body of every error response
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct ErrorResponse {
+    pub message: String,
+    // per-field messages, only when a payload was rejected
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, Vec<String>>,
 }
 
 impl CustomError {
@@ -16,6 +31,7 @@ impl CustomError {
         CustomError {
             error_status_code,
             error_message,
+            field_errors: BTreeMap::new(),
         }
     }
 }
@@ -31,13 +47,46 @@ impl From<DieselError> for CustomError {
         match error {
             DieselError::DatabaseError(_, err) => CustomError::new(409, err.message().to_string()),
             DieselError::NotFound => {
-                CustomError::new(404, "The Employeef record not found".to_string())
+                CustomError::new(404, "Record not found".to_string())
             }
             err => CustomError::new(500, format!("Unknown DIesel error: {}", err)),
         }
     }
 }
 
+impl From<BlockingError<CustomError>> for CustomError {
+    fn from(error: BlockingError<CustomError>) -> CustomError {
+        match error {
+            BlockingError::Error(error) => error,
+            BlockingError::Canceled => CustomError::new(500, "Blocking operation canceled".to_string()),
+        }
+    }
+}
+
+impl From<ValidationErrors> for CustomError {
+    fn from(errors: ValidationErrors) -> CustomError {
+        let field_errors = errors
+            .field_errors()
+            .into_iter()
+            .map(|(field, errors)| {
+                let messages = errors
+                    .iter()
+                    .map(|error| match &error.message {
+                        Some(message) => message.to_string(),
+                        None => error.code.to_string(),
+                    })
+                    .collect();
+                (field.to_string(), messages)
+            })
+            .collect();
+        CustomError {
+            error_status_code: 422,
+            error_message: "Validation failed".to_string(),
+            field_errors,
+        }
+    }
+}
+
 impl ResponseError for CustomError {
     fn error_response(&self) -> HttpResponse {
         let status_code = match StatusCode::from_u16(self.error_status_code) {
@@ -50,6 +99,9 @@ impl ResponseError for CustomError {
             false => "Internal server error".to_string(),
         };
 
-        HttpResponse::build(status_code).json( json!({ "message": error_message }))
+        HttpResponse::build(status_code).json(ErrorResponse {
+            message: error_message,
+            errors: self.field_errors.clone(),
+        })
     }
 }
@@ -0,0 +1,121 @@
+use crate::auth::Claims;
+use crate::error_handler::CustomError;
+use actix_web::http::header::{HeaderName, ETAG, IF_MATCH, IF_NONE_MATCH, VARY};
+use actix_web::{HttpRequest, HttpResponse};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+// strong tag of a versioned row
+pub fn version_tag(version: i32) -> String {
+    format!("\"{}\"", version)
+}
+
+// strong tag of a versioned row as a role sees it, a redacted body is another representation
+fn role_tag(version: i32, user: &Claims) -> String {
+    if user.role.hidden_fields().is_empty() {
+        version_tag(version)
+    } else {
+        format!("\"{}-{}\"", version, user.role.as_str())
+    }
+}
+
+// weak tag of a generated body, the same json always gets the same tag
+fn body_tag(body: &[u8]) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("W/\"{:016x}\"", hasher.finish())
+}
+
+// the comma separated tags of a header, None when it is missing
+fn header_tags(req: &HttpRequest, name: HeaderName) -> Option<Vec<String>> {
+    let value = req.headers().get(name)?.to_str().ok()?;
+    Some(
+        value
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+    )
+}
+
+pub fn precondition_failed() -> CustomError {
+    CustomError::new(412, "The employee was changed since it was read, fetch it again".to_string())
+}
+
+pub enum IfMatch {
+    Any,
+    Tags(Vec<String>),
+}
+
+impl IfMatch {
+    // updates need it, a client that never read the row can't know what it overwrites
+    pub fn required(req: &HttpRequest) -> Result<IfMatch, CustomError> {
+        match header_tags(req, IF_MATCH) {
+            Some(tags) => Ok(IfMatch::from_tags(tags)),
+            None => Err(CustomError::new(
+                428,
+                "If-Match with the ETag of the employee is required".to_string(),
+            )),
+        }
+    }
+
+    pub fn optional(req: &HttpRequest) -> IfMatch {
+        header_tags(req, IF_MATCH).map_or(IfMatch::Any, IfMatch::from_tags)
+    }
+
+    fn from_tags(tags: Vec<String>) -> IfMatch {
+        if tags.iter().any(|tag| tag == "*") {
+            IfMatch::Any
+        } else {
+            IfMatch::Tags(tags)
+        }
+    }
+
+    // strong comparison, weak tags and the tags of redacted bodies never match
+    pub fn check(&self, version: i32) -> Result<(), CustomError> {
+        let current = version_tag(version);
+        match self {
+            IfMatch::Tags(tags) if !tags.contains(&current) => Err(precondition_failed()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn json_response<T: Serialize>(req: &HttpRequest, tag: Option<String>, body: &T) -> Result<HttpResponse, CustomError> {
+    let body = serde_json::to_vec(body)
+        .map_err(|e| CustomError::new(500, format!("Failed serializing response: {}", e)))?;
+    let tag = tag.unwrap_or_else(|| body_tag(&body));
+    // weak comparison, W/"x" and "x" are the same
+    let opaque = |tag: &str| tag.trim_start_matches("W/").to_string();
+    let not_modified = header_tags(req, IF_NONE_MATCH).is_some_and(|tags| {
+        tags.iter().any(|seen| seen == "*" || opaque(seen) == opaque(&tag))
+    });
+    if not_modified {
+        return Ok(HttpResponse::NotModified()
+            .header(ETAG, tag)
+            .header(VARY, "Authorization")
+            .finish());
+    }
+    // the body depends on the role behind the token
+    Ok(HttpResponse::Ok()
+        .header(ETAG, tag)
+        .header(VARY, "Authorization")
+        .content_type("application/json")
+        .body(body))
+}
+
+// a single row redacted for user, tagged with its version
+pub fn versioned<T: Serialize>(
+    req: &HttpRequest,
+    user: &Claims,
+    version: i32,
+    body: &T,
+) -> Result<HttpResponse, CustomError> {
+    json_response(req, Some(role_tag(version, user)), &user.redact(body)?)
+}
+
+// a list or anything else without a version, tagged with a hash of the body
+pub fn hashed<T: Serialize>(req: &HttpRequest, body: &T) -> Result<HttpResponse, CustomError> {
+    json_response(req, None, body)
+}
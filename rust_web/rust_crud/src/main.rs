@@ -1,36 +1,119 @@
+// diesel 1.x derives expand to impls inside const blocks
+#![allow(non_local_definitions)]
+
 #[macro_use]
 extern crate diesel;
 #[macro_use]
 extern crate diesel_migrations;
 
+mod auth;
+mod config;
+mod departments;
 mod employees;
+mod reports;
 mod db;
 mod schema;
 mod error_handler;
+mod etag;
+mod openapi;
+
+use crate::auth::{Auth, Authentication, Users};
+use crate::config::{Config, TlsConfig};
+use crate::error_handler::CustomError;
+use actix_web::middleware::Logger;
+use actix_web::{web, App, HttpServer};
+use listenfd::ListenFd;
+use log::{info, warn};
+use rand::RngCore;
+use rustls::internal::pemfile::{certs, pkcs8_private_keys, rsa_private_keys};
+use rustls::{NoClientAuth, ServerConfig};
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::Path;
 
-use actix_web::{web, App, HttpRequest, HttpServer, Responder};
+fn load_tls(tls: &TlsConfig) -> io::Result<ServerConfig> {
+    let invalid = |what: &str, path: &Path| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("invalid {} in {}", what, path.display()))
+    };
+    let certs = certs(&mut BufReader::new(File::open(&tls.cert)?))
+        .map_err(|_| invalid("certificate", &tls.cert))?;
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(File::open(&tls.key)?))
+        .map_err(|_| invalid("private key", &tls.key))?;
+    if keys.is_empty() {
+        keys = rsa_private_keys(&mut BufReader::new(File::open(&tls.key)?))
+            .map_err(|_| invalid("private key", &tls.key))?;
+    }
+    if keys.is_empty() {
+        return Err(invalid("private key", &tls.key));
+    }
+    let mut config = ServerConfig::new(NoClientAuth::new());
+    config
+        .set_single_cert(certs, keys.remove(0))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    Ok(config)
+}
 
-async fn welcome(request: HttpRequest) -> impl Responder {
-    let name = request.match_info().get("name").unwrap_or("World");
-    format!("Hello {}!", &name)
+// every route of the api, also used by the tests
+fn init_routes(config: &mut web::ServiceConfig) {
+    auth::init_routes(config);
+    employees::init_routes(config);
+    departments::init_routes(config);
+    reports::init_routes(config);
+    openapi::init_routes(config);
 }
 
 #[actix_rt::main]
-async fn main() -> std::io::Result<()> {
-    println!("running");
-    /*
-    let addr = "127.0.0.1:8000".parse().unwrap();
-    let socket = TcpSocket::new_v4()?;
-    socket.set_reuseaddr(true)?;
-    assert!(socket.reuseaddr().unwrap());
-    socket.bind(addr)?;
-    */
-
-    HttpServer::new(|| {
+async fn main() -> io::Result<()> {
+    let config = Config::load().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    env_logger::Builder::new().parse_filters(&config.log_level).init();
+
+    let to_io = |e: CustomError| io::Error::other(e.to_string());
+    let pool = db::pool(&config.database_url, config.pool_size).map_err(to_io)?;
+    db::init(&pool).map_err(to_io)?;
+
+    let auth = match &config.jwt_secret {
+        Some(secret) => Auth::new(secret.as_bytes()),
+        None => {
+            warn!("no jwt secret configured, tokens will not survive a restart");
+            let mut secret = [0u8; 32];
+            rand::thread_rng().fill_bytes(&mut secret);
+            Auth::new(&secret)
+        }
+    };
+    if let Some(password) = &config.admin_password {
+        if Users::ensure_admin(&pool, &auth, password).map_err(to_io)? {
+            info!("created the admin user");
+        }
+    }
+    let auth = web::Data::new(auth);
+    if let Some(retention_days) = config.retention_days {
+        employees::spawn_purge(pool.clone(), retention_days);
+    }
+
+    let mut server = HttpServer::new(move || {
         App::new()
-            .configure(employees::init_routes)
-    })
-    .bind( "127.0.0.1:8000")?
-    .run()
-    .await
+            .wrap(Authentication)
+            .wrap(Logger::default())
+            .data(pool.clone())
+            .app_data(auth.clone())
+            .configure(init_routes)
+    });
+    if let Some(workers) = config.workers {
+        server = server.workers(workers);
+    }
+    let tls = match &config.tls {
+        Some(tls) => Some(load_tls(tls)?),
+        None => None,
+    };
+
+    // reuse the socket handed over by systemfd/catflap so reloads don't drop connections
+    let mut listenfd = ListenFd::from_env();
+    server = match (listenfd.take_tcp_listener(0)?, tls) {
+        (Some(listener), Some(tls)) => server.listen_rustls(listener, tls)?,
+        (Some(listener), None) => server.listen(listener)?,
+        (None, Some(tls)) => server.bind_rustls(&config.bind, tls)?,
+        (None, None) => server.bind(&config.bind)?,
+    };
+    info!("running");
+    server.run().await
 }
@@ -0,0 +1,173 @@
+use crate::error_handler::ErrorResponse;
+use crate::{auth, departments, employees, reports};
+use actix_web::{get, web, HttpResponse};
+use serde::Serialize;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+// body of the DELETE routes
+#[derive(Serialize, ToSchema)]
+pub struct Deleted {
+    pub deleted: usize,
+}
+
+// the browser loads Redoc from the CDN, the README says so
+const DOCS_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+    <title>rust_crud API</title>
+    <meta charset="utf-8">
+</head>
+<body>
+    <redoc spec-url="/openapi.json"></redoc>
+    <script src="https://cdn.jsdelivr.net/npm/redoc@2.1.5/bundles/redoc.standalone.js"></script>
+</body>
+</html>
+"#;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+    }
+}
+
+// the routes of each module are described next to them, see their *Api structs
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "rust_crud", description = "Employees and departments"),
+    paths(spec_json, docs),
+    components(schemas(ErrorResponse, Deleted)),
+    security(("bearer" = [])),
+    modifiers(&BearerAuth)
+)]
+struct ApiDoc;
+
+pub fn spec() -> utoipa::openapi::OpenApi {
+    let mut spec = ApiDoc::openapi();
+    // Cargo.toml names no license, utoipa would put in an empty one
+    spec.info.license = None;
+    spec.merge(auth::AuthApi::openapi());
+    spec.merge(employees::EmployeesApi::openapi());
+    spec.merge(departments::DepartmentsApi::openapi());
+    spec.merge(reports::ReportsApi::openapi());
+    spec
+}
+
+/// This document
+#[utoipa::path(get, path = "/openapi.json", tag = "docs", security(()), responses((status = 200, description = "OpenAPI 3 document")))]
+#[get("/openapi.json")]
+async fn spec_json() -> HttpResponse {
+    HttpResponse::Ok().json(spec())
+}
+
+/// Redoc page rendering this document
+#[utoipa::path(get, path = "/docs", tag = "docs", security(()), responses((status = 200, description = "HTML page", content_type = "text/html")))]
+#[get("/docs")]
+async fn docs() -> HttpResponse {
+    HttpResponse::Ok().content_type("text/html; charset=utf-8").body(DOCS_PAGE)
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(spec_json);
+    config.service(docs);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::testing::{bearer, test_app};
+    use crate::auth::Role;
+    use crate::db;
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::test;
+    use serde_json::Value;
+    use std::collections::BTreeSet;
+
+    // every file with route handlers, a new one has to be added here
+    const ROUTE_SOURCES: [&str; 5] = [
+        include_str!("auth/route.rs"),
+        include_str!("departments/route.rs"),
+        include_str!("employees/route.rs"),
+        include_str!("reports/route.rs"),
+        include_str!("openapi.rs"),
+    ];
+    const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+    // (method, path) of every #[get("..")] and friends on a handler
+    fn declared_routes() -> BTreeSet<(String, String)> {
+        let mut routes = BTreeSet::new();
+        for line in ROUTE_SOURCES.iter().flat_map(|source| source.lines()) {
+            for method in METHODS {
+                let attribute = format!("#[{}(\"", method);
+                if let Some(rest) = line.trim().strip_prefix(attribute.as_str()) {
+                    let path = rest.split('"').next().unwrap();
+                    routes.insert((method.to_string(), path.to_string()));
+                }
+            }
+        }
+        routes
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let spec = serde_json::to_value(spec()).unwrap();
+        let mut routes = BTreeSet::new();
+        for (path, item) in spec["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys().filter(|key| METHODS.contains(&key.as_str())) {
+                routes.insert((method.clone(), path.clone()));
+            }
+        }
+        routes
+    }
+
+    #[test]
+    fn test_spec_documents_every_route() {
+        let declared = declared_routes();
+        assert!(declared.len() > 20);
+        assert_eq!(documented_routes(), declared);
+    }
+
+    #[actix_rt::test]
+    async fn test_documented_routes_are_registered() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+
+        let mut routes = documented_routes();
+        routes.insert(("get".to_string(), "/not-a-route".to_string()));
+        let mut unregistered = Vec::new();
+        for (method, path) in routes {
+            let req = test::TestRequest::default()
+                .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+                .header("Authorization", bearer(Role::Admin))
+                .uri(&path.replace("{id}", "0"))
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            let status = resp.status();
+            // a handler's 404 has a message, the router's has no body
+            let body = test::read_body(resp).await;
+            if status == StatusCode::METHOD_NOT_ALLOWED || (status == StatusCode::NOT_FOUND && body.is_empty()) {
+                unregistered.push(format!("{} {}", method, path));
+            }
+        }
+        assert_eq!(unregistered, vec!["get /not-a-route"]);
+    }
+
+    #[actix_rt::test]
+    async fn test_docs_need_no_token() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        let req = test::TestRequest::get().uri("/openapi.json").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let spec: Value = test::read_body_json(resp).await;
+        assert_eq!(spec["openapi"], Value::from("3.1.0"));
+        assert!(spec["components"]["schemas"]["ErrorResponse"].is_object());
+        assert_eq!(spec["paths"]["/employeess"]["get"]["deprecated"], Value::from(true));
+
+        let req = test::TestRequest::get().uri("/docs").to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
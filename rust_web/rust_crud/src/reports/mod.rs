@@ -0,0 +1,5 @@
+mod route;
+mod model;
+
+pub use route::{init_routes, ReportsApi};
+pub use model::*;
@@ -0,0 +1,144 @@
+use crate::db::{self, Pool};
+use crate::departments::Departments;
+use crate::employees::EmployeeQuery;
+use crate::error_handler::CustomError;
+use crate::schema::employees;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+const DEFAULT_BUCKETS: i32 = 10;
+const MAX_BUCKETS: i32 = 100;
+
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct DepartmentReport {
+    pub department_id: i32,
+    pub department: String,
+    pub headcount: i64,
+    pub total_salary: i64,
+    pub avg_salary: f64,
+    pub min_salary: i32,
+    pub max_salary: i32,
+    pub avg_age: f64,
+}
+
+// salaries from `from` up to and including `to`
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct Bucket {
+    pub from: i32,
+    pub to: i32,
+    pub count: i64,
+}
+
+#[derive(Serialize, Debug, PartialEq, ToSchema)]
+pub struct SalaryHistogram {
+    pub bucket_width: i64,
+    pub buckets: Vec<Bucket>,
+}
+
+impl DepartmentReport {
+    // one row per department that has matching employees, ordered by department name
+    pub fn find_all(pool: &Pool, filters: &EmployeeQuery) -> Result<Vec<Self>, CustomError> {
+        let conn = db::connection(pool)?;
+        // the aggregates are typed sql, diesel 1.x doesn't allow aggregate functions in a select tuple
+        let rows = filters
+            .filtered()
+            .select((
+                employees::department_id,
+                sql::<BigInt>("COUNT(*)"),
+                sql::<Nullable<BigInt>>("SUM(employees.salary)"),
+                sql::<Nullable<Integer>>("MIN(employees.salary)"),
+                sql::<Nullable<Integer>>("MAX(employees.salary)"),
+                sql::<Nullable<BigInt>>("SUM(employees.age)"),
+            ))
+            .group_by(employees::department_id)
+            .load::<(i32, i64, Option<i64>, Option<i32>, Option<i32>, Option<i64>)>(&conn)?;
+
+        let ids: Vec<i32> = rows.iter().map(|row| row.0).collect();
+        let names: HashMap<i32, String> = Departments::find_by_ids(&conn, &ids)?
+            .into_iter()
+            .map(|department| (department.id, department.name))
+            .collect();
+        let mut reports: Vec<DepartmentReport> = rows
+            .into_iter()
+            .map(|(department_id, headcount, total_salary, min_salary, max_salary, total_age)| {
+                let total_salary = total_salary.unwrap_or(0);
+                DepartmentReport {
+                    department_id,
+                    department: names.get(&department_id).cloned().unwrap_or_default(),
+                    headcount,
+                    total_salary,
+                    avg_salary: total_salary as f64 / headcount as f64,
+                    min_salary: min_salary.unwrap_or(0),
+                    max_salary: max_salary.unwrap_or(0),
+                    avg_age: total_age.unwrap_or(0) as f64 / headcount as f64,
+                }
+            })
+            .collect();
+        reports.sort_by(|a, b| a.department.cmp(&b.department));
+        Ok(reports)
+    }
+}
+
+impl SalaryHistogram {
+    // equal width buckets from the lowest to the highest matching salary
+    pub fn find(pool: &Pool, filters: &EmployeeQuery, buckets: Option<i32>) -> Result<Self, CustomError> {
+        let buckets = buckets.unwrap_or(DEFAULT_BUCKETS);
+        if !(1..=MAX_BUCKETS).contains(&buckets) {
+            return Err(CustomError::new(400, format!("buckets must be between 1 and {}", MAX_BUCKETS)));
+        }
+        let conn = db::connection(pool)?;
+        let (lowest, highest) = filters
+            .filtered()
+            .select((
+                sql::<Nullable<Integer>>("MIN(employees.salary)"),
+                sql::<Nullable<Integer>>("MAX(employees.salary)"),
+            ))
+            .first::<(Option<i32>, Option<i32>)>(&conn)?;
+        let (lowest, highest) = match (lowest, highest) {
+            (Some(lowest), Some(highest)) => (lowest, highest),
+            _ => {
+                return Ok(SalaryHistogram {
+                    bucket_width: 0,
+                    buckets: Vec::new(),
+                })
+            }
+        };
+        // in i64, salaries near the ends of i32 overflow it, the highest lands in the last bucket at most
+        let (lowest, highest) = (i64::from(lowest), i64::from(highest));
+        let width = (highest - lowest) / i64::from(buckets) + 1;
+
+        // only aggregates are selected, postgres won't match a bound expression in both
+        // SELECT and GROUP BY, the bucket index is worked out from the lowest salary in it
+        let counts = filters
+            .filtered()
+            .select((sql::<Nullable<Integer>>("MIN(employees.salary)"), sql::<BigInt>("COUNT(*)")))
+            .group_by(sql::<BigInt>(&format!(
+                "(CAST(employees.salary AS BIGINT) - {}) / {}",
+                lowest, width
+            )))
+            .load::<(Option<i32>, i64)>(&conn)?;
+        // buckets past the highest salary are left out, the last one ends at it
+        let mut histogram: Vec<Bucket> = (0..i64::from(buckets))
+            .map(|index| lowest + index * width)
+            .take_while(|from| *from <= highest)
+            .map(|from| Bucket {
+                from: from as i32,
+                to: (from + width - 1).min(highest) as i32,
+                count: 0,
+            })
+            .collect();
+        for (salary, count) in counts {
+            if let Some(salary) = salary {
+                histogram[((i64::from(salary) - lowest) / width) as usize].count = count;
+            }
+        }
+        Ok(SalaryHistogram {
+            bucket_width: width,
+            buckets: histogram,
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use crate::auth::{Claims, Role};
+use crate::db::{self, Pool};
+use crate::employees::EmployeeQuery;
+use crate::error_handler::{CustomError, ErrorResponse};
+use crate::reports::{Bucket, DepartmentReport, SalaryHistogram};
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use utoipa::{IntoParams, OpenApi};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(departments, salary_histogram),
+    components(schemas(DepartmentReport, Bucket, SalaryHistogram))
+)]
+pub struct ReportsApi;
+
+// both reports take the filters of GET /employees, paging and sorting are ignored
+/// Headcount, salaries and ages per department
+#[utoipa::path(
+    get, path = "/reports/departments", tag = "reports",
+    params(EmployeeQuery),
+    responses(
+        (status = 200, body = Vec<DepartmentReport>),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+#[get("/reports/departments")]
+async fn departments(
+    pool: web::Data<Pool>,
+    user: Claims,
+    filters: web::Query<EmployeeQuery>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    if filters.include_deleted {
+        user.require(Role::Admin)?;
+    }
+    let reports = db::run(&pool, move |pool| DepartmentReport::find_all(pool, &filters)).await?;
+    Ok(HttpResponse::Ok().json(reports))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HistogramQuery {
+    buckets: Option<i32>,
+}
+
+/// Matching employees counted in equal width salary buckets
+#[utoipa::path(
+    get, path = "/reports/salary-histogram", tag = "reports",
+    params(EmployeeQuery, HistogramQuery),
+    responses(
+        (status = 200, body = SalaryHistogram),
+        (status = 400, body = ErrorResponse),
+        (status = 403, body = ErrorResponse),
+    )
+)]
+#[get("/reports/salary-histogram")]
+async fn salary_histogram(
+    pool: web::Data<Pool>,
+    user: Claims,
+    filters: web::Query<EmployeeQuery>,
+    query: web::Query<HistogramQuery>,
+) -> Result<HttpResponse, CustomError> {
+    user.require(Role::HrEditor)?;
+    if filters.include_deleted {
+        user.require(Role::Admin)?;
+    }
+    let buckets = query.buckets;
+    let histogram = db::run(&pool, move |pool| SalaryHistogram::find(pool, &filters, buckets)).await?;
+    Ok(HttpResponse::Ok().json(histogram))
+}
+
+pub fn init_routes(config: &mut web::ServiceConfig) {
+    config.service(departments);
+    config.service(salary_histogram);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::testing::{bearer, test_app};
+    use crate::departments::{Department, Departments};
+    use crate::employees::{Employee, Employees};
+    use actix_web::{http::StatusCode, test};
+    use serde_json::{json, Value};
+
+    // "Dept" (id 1) holds the seeded admin, salary 3000 and age 30
+    fn seed(pool: &Pool) -> i32 {
+        let sales = Departments::create(pool, Department { name: "Sales".to_string() }).unwrap();
+        for (department_id, salary, age) in [(sales.id, 1000, 20), (sales.id, 2000, 40), (1, 5000, 50)] {
+            let employee = Employee {
+                first_name: "Test".to_string(),
+                last_name: "Person".to_string(),
+                department_id,
+                salary,
+                age,
+            };
+            Employees::create(pool, "test", employee).unwrap();
+        }
+        sales.id
+    }
+
+    #[actix_rt::test]
+    async fn test_department_report() {
+        let pool = db::test_pool();
+        let sales = seed(&pool);
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/reports/departments")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let reports: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            reports,
+            json!([
+                {
+                    "department_id": 1, "department": "Dept", "headcount": 2, "total_salary": 8000,
+                    "avg_salary": 4000.0, "min_salary": 3000, "max_salary": 5000, "avg_age": 40.0
+                },
+                {
+                    "department_id": sales, "department": "Sales", "headcount": 2, "total_salary": 3000,
+                    "avg_salary": 1500.0, "min_salary": 1000, "max_salary": 2000, "avg_age": 30.0
+                }
+            ])
+        );
+
+        // same filters as the list
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/reports/departments?min_age=35&department=sales")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let reports: Value = test::read_body_json(resp).await;
+        assert_eq!(reports.as_array().unwrap().len(), 1);
+        assert_eq!(reports[0]["headcount"], json!(1));
+        assert_eq!(reports[0]["total_salary"], json!(2000));
+    }
+
+    #[actix_rt::test]
+    async fn test_salary_histogram() {
+        let pool = db::test_pool();
+        seed(&pool);
+        let mut app = test_app(&pool).await;
+
+        // salaries 1000, 2000, 3000 and 5000
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/reports/salary-histogram?buckets=4")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let histogram: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            histogram,
+            json!({
+                "bucket_width": 1001,
+                "buckets": [
+                    { "from": 1000, "to": 2000, "count": 2 },
+                    { "from": 2001, "to": 3001, "count": 1 },
+                    { "from": 3002, "to": 4002, "count": 0 },
+                    { "from": 4003, "to": 5000, "count": 1 }
+                ]
+            })
+        );
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/reports/salary-histogram?min_salary=9000")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let histogram: Value = test::read_body_json(resp).await;
+        assert_eq!(histogram, json!({ "bucket_width": 0, "buckets": [] }));
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/reports/salary-histogram?buckets=0")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_rt::test]
+    async fn test_salary_histogram_up_to_i32_max() {
+        let pool = db::test_pool();
+        seed(&pool);
+        for salary in [0, i32::MAX] {
+            let employee = Employee {
+                first_name: "Test".to_string(),
+                last_name: "Person".to_string(),
+                department_id: 1,
+                salary,
+                age: 30,
+            };
+            Employees::create(&pool, "test", employee).unwrap();
+        }
+        let mut app = test_app(&pool).await;
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/reports/salary-histogram?buckets=1")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let histogram: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            histogram,
+            json!({
+                "bucket_width": 2147483648i64,
+                "buckets": [{ "from": 0, "to": 2147483647, "count": 6 }]
+            })
+        );
+
+        let req = test::TestRequest::get()
+            .header("Authorization", bearer(Role::HrEditor))
+            .uri("/reports/salary-histogram?buckets=2")
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let histogram: Value = test::read_body_json(resp).await;
+        assert_eq!(
+            histogram,
+            json!({
+                "bucket_width": 1073741824,
+                "buckets": [
+                    { "from": 0, "to": 1073741823, "count": 5 },
+                    { "from": 1073741824, "to": 2147483647, "count": 1 }
+                ]
+            })
+        );
+    }
+
+    #[actix_rt::test]
+    async fn test_viewer_cannot_see_reports() {
+        let pool = db::test_pool();
+        let mut app = test_app(&pool).await;
+        for uri in ["/reports/departments", "/reports/salary-histogram"] {
+            let req = test::TestRequest::get()
+                .header("Authorization", bearer(Role::Viewer))
+                .uri(uri)
+                .to_request();
+            let resp = test::call_service(&mut app, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        }
+    }
+}
@@ -1,10 +1,48 @@
+table! {
+    departments (id) {
+        id -> Integer,
+        name -> Text,
+    }
+}
+
+table! {
+    employee_audit (id) {
+        id -> Integer,
+        employee_id -> Integer,
+        actor -> Text,
+        changed_at -> Text,
+        operation -> Text,
+        changes -> Text,
+    }
+}
+
 table! {
     employees (id) {
         id -> Integer,
         first_name -> Text,
         last_name -> Text,
-        department -> Text,
+        department_id -> Integer,
         salary -> Integer,
         age -> Integer,
+        version -> Integer,
+        deleted_at -> Nullable<Text>,
+    }
+}
+
+table! {
+    users (id) {
+        id -> Integer,
+        username -> Text,
+        password_hash -> Text,
+        role -> Text,
     }
 }
+
+joinable!(employees -> departments (department_id));
+
+allow_tables_to_appear_in_same_query!(
+    departments,
+    employee_audit,
+    employees,
+    users,
+);
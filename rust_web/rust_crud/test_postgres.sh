@@ -0,0 +1,26 @@
+#!/bin/sh
+# runs the tests against a throwaway postgres server, needs initdb and pg_ctl on the PATH
+# usage: ./test_postgres.sh [cargo test args]
+set -e
+ROOT=$(cd "$(dirname "$0")" && pwd)
+
+PORT=${PGPORT:-54329}
+DATA=$(mktemp -d)
+# initdb refuses to run as root
+if [ "$(id -u)" = 0 ]; then
+    chown postgres "$DATA"
+    AS_PG="runuser -u postgres --"
+fi
+
+# the postgres user can't always enter the repo
+cd /
+# utf-8 so lower() and to_tsvector() fold non-ascii letters like the app expects
+$AS_PG initdb -D "$DATA" -U postgres -A trust -E UTF8 --locale=C.UTF-8 >/dev/null
+$AS_PG pg_ctl -D "$DATA" -l "$DATA/server.log" -w \
+    -o "-p $PORT -k $DATA -c listen_addresses=127.0.0.1 -c fsync=off" start >/dev/null
+trap 'cd / && $AS_PG pg_ctl -D "$DATA" -m immediate stop >/dev/null; rm -rf "$DATA"' EXIT
+
+cd "$ROOT"
+psql -q -h 127.0.0.1 -p "$PORT" -U postgres -c "CREATE DATABASE rust_crud_test"
+TEST_DATABASE_URL="postgres://postgres@127.0.0.1:$PORT/rust_crud_test" \
+    cargo test --features postgres "$@"